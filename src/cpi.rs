@@ -0,0 +1,193 @@
+use solana_program::account_info::AccountInfo;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program::{invoke, invoke_signed};
+use solana_program::program_error::ProgramError;
+
+use crate::instruction::{
+    claim_reward, create_stake_account, deposit, emergency_withdraw, query_pending_reward, withdraw,
+};
+use crate::return_data::{ClaimedReward, PendingReward, ReturnData, StakeBalance};
+
+/// Invoke `CreateStakeAccount` on the staking program.
+pub fn create_stake_account_cpi<'a>(
+    staking_program: AccountInfo<'a>,
+    stake_account: AccountInfo<'a>,
+    staking_pool: AccountInfo<'a>,
+    stake_account_owner: AccountInfo<'a>,
+    rent: AccountInfo<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = create_stake_account(
+        *staking_program.key,
+        *stake_account.key,
+        *staking_pool.key,
+        *stake_account_owner.key,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            stake_account,
+            staking_pool,
+            stake_account_owner,
+            rent,
+            staking_program,
+        ],
+        signers_seeds,
+    )
+}
+
+/// Invoke `Deposit` on the staking program, signing as `authority`, and decode its return
+/// data.
+pub fn deposit_cpi<'a>(
+    staking_program: AccountInfo<'a>,
+    amount: u64,
+    authority: AccountInfo<'a>,
+    stake_account: AccountInfo<'a>,
+    staking_pool: AccountInfo<'a>,
+    clock: AccountInfo<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> Result<StakeBalance, ProgramError> {
+    let ix = deposit(
+        *staking_program.key,
+        amount,
+        *authority.key,
+        *stake_account.key,
+        *staking_pool.key,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            authority,
+            stake_account,
+            staking_pool,
+            clock,
+            staking_program.clone(),
+        ],
+        signers_seeds,
+    )?;
+    StakeBalance::get(staking_program.key)
+}
+
+/// Invoke `Withdraw` on the staking program, signing as `authority`, and decode its return
+/// data.
+pub fn withdraw_cpi<'a>(
+    staking_program: AccountInfo<'a>,
+    amount: u64,
+    authority: AccountInfo<'a>,
+    stake_account: AccountInfo<'a>,
+    staking_pool: AccountInfo<'a>,
+    clock: AccountInfo<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> Result<StakeBalance, ProgramError> {
+    let ix = withdraw(
+        *staking_program.key,
+        amount,
+        *authority.key,
+        *stake_account.key,
+        *staking_pool.key,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            authority,
+            stake_account,
+            staking_pool,
+            clock,
+            staking_program.clone(),
+        ],
+        signers_seeds,
+    )?;
+    StakeBalance::get(staking_program.key)
+}
+
+/// Invoke `ClaimReward` on the staking program, signing as `stake_account_owner`. Pass both
+/// `sub_reward_pool` and `sub_reward_destination` to also claim the sub reward of a dual
+/// reward pool. Decodes the return data.
+#[allow(clippy::too_many_arguments)]
+pub fn claim_reward_cpi<'a>(
+    staking_program: AccountInfo<'a>,
+    stake_account_owner: AccountInfo<'a>,
+    stake_account: AccountInfo<'a>,
+    staking_pool: AccountInfo<'a>,
+    reward_token_pool: AccountInfo<'a>,
+    reward_destination: AccountInfo<'a>,
+    staking_program_derived: AccountInfo<'a>,
+    clock: AccountInfo<'a>,
+    token_program: AccountInfo<'a>,
+    sub_reward_pool: Option<AccountInfo<'a>>,
+    sub_reward_destination: Option<AccountInfo<'a>>,
+    signers_seeds: &[&[&[u8]]],
+) -> Result<ClaimedReward, ProgramError> {
+    let ix = claim_reward(
+        *staking_program.key,
+        *stake_account_owner.key,
+        *stake_account.key,
+        *staking_pool.key,
+        *reward_token_pool.key,
+        *reward_destination.key,
+        sub_reward_pool.as_ref().map(|account| *account.key),
+        sub_reward_destination.as_ref().map(|account| *account.key),
+    );
+    let mut account_infos = vec![
+        stake_account_owner,
+        stake_account,
+        staking_pool,
+        reward_token_pool,
+        reward_destination,
+        staking_program_derived,
+        clock,
+        token_program,
+    ];
+    if let (Some(sub_reward_pool), Some(sub_reward_destination)) =
+        (sub_reward_pool, sub_reward_destination)
+    {
+        account_infos.push(sub_reward_pool);
+        account_infos.push(sub_reward_destination);
+    }
+    account_infos.push(staking_program.clone());
+    invoke_signed(&ix, &account_infos, signers_seeds)?;
+    ClaimedReward::get(staking_program.key)
+}
+
+/// Invoke `EmergencyWithdraw` on the staking program, signing as `authority`.
+pub fn emergency_withdraw_cpi<'a>(
+    staking_program: AccountInfo<'a>,
+    authority: AccountInfo<'a>,
+    stake_account: AccountInfo<'a>,
+    staking_pool: AccountInfo<'a>,
+    clock: AccountInfo<'a>,
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = emergency_withdraw(
+        *staking_program.key,
+        *authority.key,
+        *stake_account.key,
+        *staking_pool.key,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            authority,
+            stake_account,
+            staking_pool,
+            clock,
+            staking_program,
+        ],
+        signers_seeds,
+    )
+}
+
+/// Invoke `QueryPendingReward` on the staking program and decode its return data.
+pub fn query_pending_reward_cpi<'a>(
+    staking_program: AccountInfo<'a>,
+    stake_account: AccountInfo<'a>,
+    staking_pool: AccountInfo<'a>,
+    clock: AccountInfo<'a>,
+) -> Result<PendingReward, ProgramError> {
+    let ix = query_pending_reward(*staking_program.key, *stake_account.key, *staking_pool.key);
+    invoke(
+        &ix,
+        &[stake_account, staking_pool, clock, staking_program.clone()],
+    )?;
+    PendingReward::get(staking_program.key)
+}
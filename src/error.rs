@@ -1,10 +1,14 @@
+use std::convert::TryFrom;
+
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 use thiserror::Error;
 
 use crate::solana_program::decode_error::DecodeError;
+use crate::solana_program::instruction::InstructionError;
 use crate::solana_program::msg;
 use crate::solana_program::program_error::{PrintProgramError, ProgramError};
+use crate::solana_program::pubkey::Pubkey;
 
 #[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
 pub enum StakingError {
@@ -73,6 +77,112 @@ pub enum StakingError {
     //25
     #[error("Cannot reduce reward smaller than zero")]
     ReduceRewardTooMuch,
+    #[error("Staking pool reward period has not ended")]
+    StakingPoolNotEnded,
+    #[error("Staking pool still has deposits")]
+    StakingPoolNotEmpty,
+    #[error("Reward token pool still holds tokens")]
+    RewardTokenPoolNotEmpty,
+    #[error("Staking pool has not started yet")]
+    StakingPoolNotStarted,
+    #[error("Deposit exceeds the staking pool size limit")]
+    PoolSizeLimitExceeded,
+    #[error("Deposit exceeds the stake account deposit limit")]
+    AccountDepositLimitExceeded,
+    #[error("Stake account owner is not allowlisted")]
+    NotAllowlisted,
+    #[error("Reward distributor cannot feed more staking pools")]
+    DistributorFull,
+    #[error("Staking pool is not fed by the reward distributor")]
+    PoolNotInDistributor,
+    #[error("Gauge cannot split emissions between more staking pools")]
+    GaugeFull,
+    #[error("Gauge epoch has not ended")]
+    GaugeEpochNotEnded,
+    #[error("Gauge vote is already committed in this epoch")]
+    GaugeVoteAlreadyCommitted,
+    #[error("Stake account is locked")]
+    StakeLocked,
+    #[error("Lock end is before the current lock end or beyond the maximum lock duration")]
+    InvalidLockEnd,
+    #[error("Staking program did not set return data")]
+    MissingReturnData,
+    #[error("Staking pool has a different time basis")]
+    TimeBasisMismatch,
+    #[error("Gauge epoch has ended")]
+    GaugeEpochEnded,
+}
+
+impl StakingError {
+    /// Map a custom program error code back to the staking error it was produced from.
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::from_u32(code)
+    }
+}
+
+impl TryFrom<ProgramError> for StakingError {
+    type Error = ProgramError;
+
+    fn try_from(e: ProgramError) -> Result<Self, Self::Error> {
+        match e {
+            ProgramError::Custom(code) => Self::from_code(code).ok_or(e),
+            _ => Err(e),
+        }
+    }
+}
+
+/// Extract the staking error from the fields of a `TransactionError::InstructionError`,
+/// returning it with the index of the failing instruction.
+pub fn parse_instruction_error(
+    instruction_index: u8,
+    error: &InstructionError,
+) -> Option<(u8, StakingError)> {
+    match error {
+        InstructionError::Custom(code) => {
+            StakingError::from_code(*code).map(|e| (instruction_index, e))
+        }
+        _ => None,
+    }
+}
+
+/// Extract the staking error from a `TransactionError::InstructionError`, returning it with
+/// the index of the failing instruction. Takes anything whose displayed message contains that
+/// of the `TransactionError`, such as the error itself or a client error wrapping it, since the
+/// transaction error type is not available to this crate to match on.
+pub fn parse_transaction_error<E: std::fmt::Display>(error: &E) -> Option<(u8, StakingError)> {
+    const PREFIX: &str = "Error processing Instruction ";
+    let message = error.to_string();
+    let start = message.find(PREFIX)? + PREFIX.len();
+    let (instruction_index, instruction_error) = message[start..].split_once(": ")?;
+    let code = instruction_error.strip_prefix("custom program error: 0x")?;
+    let code = code
+        .find(|c: char| !c.is_ascii_hexdigit())
+        .map_or(code, |end| &code[..end]);
+    let instruction_index = instruction_index.parse().ok()?;
+    let code = u32::from_str_radix(code, 16).ok()?;
+    parse_instruction_error(instruction_index, &InstructionError::Custom(code))
+}
+
+/// Extract the staking error from the log messages of a failed transaction, returning it
+/// with the index of the top level instruction that failed.
+pub fn parse_error_from_logs<S: AsRef<str>>(
+    program_id: &Pubkey,
+    logs: &[S],
+) -> Option<(u8, StakingError)> {
+    let failed_prefix = format!("Program {} failed: custom program error: 0x", program_id);
+    let mut instruction_index: Option<u8> = None;
+    for log in logs {
+        let log = log.as_ref();
+        if log.starts_with("Program ") && log.ends_with(" invoke [1]") {
+            instruction_index = Some(instruction_index.map_or(0, |i| i.saturating_add(1)));
+        } else if let Some(code) = log.strip_prefix(&failed_prefix) {
+            let error = u32::from_str_radix(code.trim(), 16)
+                .ok()
+                .and_then(StakingError::from_code)?;
+            return Some((instruction_index.unwrap_or(0), error));
+        }
+    }
+    None
 }
 
 impl From<StakingError> for ProgramError {
@@ -95,3 +205,90 @@ impl PrintProgramError for StakingError {
         msg!(&self.to_string());
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        let mut code = 0;
+        while let Some(error) = StakingError::from_code(code) {
+            assert_eq!(error.clone() as u32, code);
+            let program_error = ProgramError::from(error.clone());
+            assert_eq!(program_error, ProgramError::Custom(code));
+            assert_eq!(StakingError::try_from(program_error), Ok(error));
+            code += 1;
+        }
+        assert_ne!(code, 0);
+    }
+
+    #[test]
+    fn other_program_errors_are_not_staking_errors() {
+        assert_eq!(
+            StakingError::try_from(ProgramError::InvalidAccountData),
+            Err(ProgramError::InvalidAccountData)
+        );
+        assert_eq!(
+            StakingError::try_from(ProgramError::Custom(u32::MAX)),
+            Err(ProgramError::Custom(u32::MAX))
+        );
+    }
+
+    #[test]
+    fn parses_instruction_error() {
+        assert_eq!(
+            parse_instruction_error(2, &InstructionError::Custom(0x15)),
+            Some((2, StakingError::from_code(0x15).unwrap()))
+        );
+        assert_eq!(
+            parse_instruction_error(2, &InstructionError::InvalidAccountData),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_transaction_error() {
+        assert_eq!(
+            parse_transaction_error(&"Error processing Instruction 1: custom program error: 0x3"),
+            Some((1, StakingError::MathOverflow))
+        );
+        assert_eq!(
+            parse_transaction_error(
+                &"Error processing Instruction 1: invalid account data for instruction"
+            ),
+            None
+        );
+        assert_eq!(parse_transaction_error(&"Blockhash not found"), None);
+    }
+
+    #[test]
+    fn parses_wrapped_transaction_error() {
+        assert_eq!(
+            parse_transaction_error(
+                &"RPC response error -32002: Transaction simulation failed: \
+                  Error processing Instruction 0: custom program error: 0x1; 3 log messages"
+            ),
+            Some((0, StakingError::AlreadyInitialized))
+        );
+    }
+
+    #[test]
+    fn parses_error_from_logs() {
+        let program_id = Pubkey::new_unique();
+        let caller = Pubkey::new_unique();
+        let logs = vec![
+            format!("Program {} invoke [1]", caller),
+            format!("Program {} success", caller),
+            format!("Program {} invoke [1]", caller),
+            format!("Program {} invoke [2]", program_id),
+            format!("Program {} failed: custom program error: 0x3", program_id),
+            format!("Program {} failed: custom program error: 0x3", caller),
+        ];
+        assert_eq!(
+            parse_error_from_logs(&program_id, &logs),
+            Some((1, StakingError::MathOverflow))
+        );
+        assert_eq!(parse_error_from_logs(&Pubkey::new_unique(), &logs), None);
+    }
+}
@@ -0,0 +1,441 @@
+use std::convert::TryInto;
+use std::mem::size_of;
+
+use solana_maths::Decimal;
+use solana_program::clock::Slot;
+use solana_program::log::sol_log_data;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::PUBKEY_BYTES;
+
+use crate::error::StakingError;
+use crate::solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+/// Prefix the runtime puts in front of data logged with `sol_log_data`.
+pub const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolInitEvent {
+    pub pool: Pubkey,
+    pub supply: u64,
+    pub duration: u64,
+    pub slot: Slot,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepositEvent {
+    pub pool: Pubkey,
+    pub stake_account: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub slot: Slot,
+    pub cumulative_rate: Decimal,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WithdrawEvent {
+    pub pool: Pubkey,
+    pub stake_account: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub slot: Slot,
+    pub cumulative_rate: Decimal,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClaimEvent {
+    pub pool: Pubkey,
+    pub stake_account: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub slot: Slot,
+    pub cumulative_rate: Decimal,
+    /// Rewards lost to the early claim penalty, not included in `amount`.
+    pub forfeited: u64,
+}
+
+/// Events emitted by the staking program as `Program data:` log lines.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StakingEvent {
+    PoolInit(PoolInitEvent),
+    Deposit(DepositEvent),
+    Withdraw(WithdrawEvent),
+    Claim(ClaimEvent),
+}
+
+impl StakingEvent {
+    /// Log the event so it can be recovered by `parse_events`.
+    pub fn emit(&self) {
+        sol_log_data(&[&self.pack()]);
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(size_of::<Self>());
+        match self {
+            Self::PoolInit(event) => {
+                buf.push(0);
+                buf.extend_from_slice(event.pool.as_ref());
+                buf.extend_from_slice(&event.supply.to_le_bytes());
+                buf.extend_from_slice(&event.duration.to_le_bytes());
+                buf.extend_from_slice(&event.slot.to_le_bytes());
+            }
+            Self::Deposit(DepositEvent {
+                pool,
+                stake_account,
+                owner,
+                amount,
+                slot,
+                cumulative_rate,
+            }) => {
+                buf.push(1);
+                Self::pack_stake_event(
+                    &mut buf,
+                    pool,
+                    stake_account,
+                    owner,
+                    *amount,
+                    *slot,
+                    cumulative_rate,
+                );
+            }
+            Self::Withdraw(WithdrawEvent {
+                pool,
+                stake_account,
+                owner,
+                amount,
+                slot,
+                cumulative_rate,
+            }) => {
+                buf.push(2);
+                Self::pack_stake_event(
+                    &mut buf,
+                    pool,
+                    stake_account,
+                    owner,
+                    *amount,
+                    *slot,
+                    cumulative_rate,
+                );
+            }
+            Self::Claim(ClaimEvent {
+                pool,
+                stake_account,
+                owner,
+                amount,
+                slot,
+                cumulative_rate,
+                forfeited,
+            }) => {
+                buf.push(3);
+                Self::pack_stake_event(
+                    &mut buf,
+                    pool,
+                    stake_account,
+                    owner,
+                    *amount,
+                    *slot,
+                    cumulative_rate,
+                );
+                buf.extend_from_slice(&forfeited.to_le_bytes());
+            }
+        };
+        buf
+    }
+
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&tag, rest) = input
+            .split_first()
+            .ok_or(StakingError::InstructionUnpackError)?;
+        let (event, rest) = match tag {
+            0 => {
+                let (pool, rest) = unpack_pubkey(rest)?;
+                let (supply, rest) = unpack_u64(rest)?;
+                let (duration, rest) = unpack_u64(rest)?;
+                let (slot, rest) = unpack_u64(rest)?;
+                (
+                    Self::PoolInit(PoolInitEvent {
+                        pool,
+                        supply,
+                        duration,
+                        slot,
+                    }),
+                    rest,
+                )
+            }
+            1..=3 => {
+                let (pool, rest) = unpack_pubkey(rest)?;
+                let (stake_account, rest) = unpack_pubkey(rest)?;
+                let (owner, rest) = unpack_pubkey(rest)?;
+                let (amount, rest) = unpack_u64(rest)?;
+                let (slot, rest) = unpack_u64(rest)?;
+                let (cumulative_rate, rest) = unpack_decimal(rest)?;
+                match tag {
+                    1 => (
+                        Self::Deposit(DepositEvent {
+                            pool,
+                            stake_account,
+                            owner,
+                            amount,
+                            slot,
+                            cumulative_rate,
+                        }),
+                        rest,
+                    ),
+                    2 => (
+                        Self::Withdraw(WithdrawEvent {
+                            pool,
+                            stake_account,
+                            owner,
+                            amount,
+                            slot,
+                            cumulative_rate,
+                        }),
+                        rest,
+                    ),
+                    _ => {
+                        let (forfeited, rest) = unpack_u64(rest)?;
+                        (
+                            Self::Claim(ClaimEvent {
+                                pool,
+                                stake_account,
+                                owner,
+                                amount,
+                                slot,
+                                cumulative_rate,
+                                forfeited,
+                            }),
+                            rest,
+                        )
+                    }
+                }
+            }
+            _ => return Err(StakingError::InstructionUnpackError.into()),
+        };
+        if rest.is_empty() {
+            Ok(event)
+        } else {
+            Err(StakingError::InstructionUnpackError.into())
+        }
+    }
+
+    fn pack_stake_event(
+        buf: &mut Vec<u8>,
+        pool: &Pubkey,
+        stake_account: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+        slot: Slot,
+        cumulative_rate: &Decimal,
+    ) {
+        buf.extend_from_slice(pool.as_ref());
+        buf.extend_from_slice(stake_account.as_ref());
+        buf.extend_from_slice(owner.as_ref());
+        buf.extend_from_slice(&amount.to_le_bytes());
+        buf.extend_from_slice(&slot.to_le_bytes());
+        let mut rate = [0; Decimal::LEN];
+        cumulative_rate.pack_into_slice(&mut rate);
+        buf.extend_from_slice(&rate);
+    }
+}
+
+/// Recover the events emitted by `program_id` from the log messages of a transaction.
+///
+/// Data logged by other programs, including programs invoked by the staking program, is
+/// skipped, as are lines that do not decode into a `StakingEvent`.
+#[cfg(feature = "events-parse")]
+pub fn parse_events<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Vec<StakingEvent> {
+    let program_id = program_id.to_string();
+    // programs of the current invocation, the innermost last
+    let mut invoke_stack: Vec<&str> = vec![];
+    let mut events = vec![];
+    for log in logs {
+        let log = log.as_ref();
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA_LOG_PREFIX) {
+            if invoke_stack.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            let event = base64::decode(data.trim())
+                .ok()
+                .and_then(|bytes| StakingEvent::unpack(&bytes).ok());
+            events.extend(event);
+        } else if let Some(rest) = log.strip_prefix("Program ") {
+            let mut words = rest.split(' ');
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => {
+                    let depth = words
+                        .next()
+                        .and_then(|depth| depth.strip_prefix('[')?.strip_suffix(']')?.parse().ok())
+                        .unwrap_or(invoke_stack.len() + 1);
+                    invoke_stack.truncate(depth.max(1) - 1);
+                    invoke_stack.push(program);
+                }
+                (Some(_), Some("success")) | (Some(_), Some("failed:")) => {
+                    invoke_stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    events
+}
+
+fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+    if input.len() < 8 {
+        return Err(StakingError::InstructionUnpackError.into());
+    }
+    let (bytes, rest) = input.split_at(8);
+    let value = bytes
+        .try_into()
+        .map(u64::from_le_bytes)
+        .map_err(|_| StakingError::InstructionUnpackError)?;
+    Ok((value, rest))
+}
+
+fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError> {
+    if input.len() < PUBKEY_BYTES {
+        return Err(StakingError::InstructionUnpackError.into());
+    }
+    let (key, rest) = input.split_at(PUBKEY_BYTES);
+    Ok((Pubkey::new(key), rest))
+}
+
+fn unpack_decimal(input: &[u8]) -> Result<(Decimal, &[u8]), ProgramError> {
+    if input.len() < Decimal::LEN {
+        return Err(StakingError::InstructionUnpackError.into());
+    }
+    let (bytes, rest) = input.split_at(Decimal::LEN);
+    Ok((Decimal::unpack_from_slice(bytes)?, rest))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn deposit_event() -> DepositEvent {
+        DepositEvent {
+            pool: Pubkey::new_unique(),
+            stake_account: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            amount: 1_000,
+            slot: 42,
+            cumulative_rate: Decimal::from(3u64),
+        }
+    }
+
+    fn events() -> Vec<StakingEvent> {
+        let DepositEvent {
+            pool,
+            stake_account,
+            owner,
+            cumulative_rate,
+            ..
+        } = deposit_event();
+        vec![
+            StakingEvent::PoolInit(PoolInitEvent {
+                pool,
+                supply: 5_000,
+                duration: 100,
+                slot: 7,
+            }),
+            StakingEvent::Deposit(deposit_event()),
+            StakingEvent::Withdraw(WithdrawEvent {
+                pool,
+                stake_account,
+                owner,
+                amount: 400,
+                slot: 43,
+                cumulative_rate,
+            }),
+            StakingEvent::Claim(ClaimEvent {
+                pool,
+                stake_account,
+                owner,
+                amount: 90,
+                slot: 44,
+                cumulative_rate,
+                forfeited: 10,
+            }),
+        ]
+    }
+
+    #[test]
+    fn events_round_trip() {
+        for event in events() {
+            assert_eq!(StakingEvent::unpack(&event.pack()), Ok(event));
+        }
+    }
+
+    #[test]
+    fn unpack_rejects_malformed_events() {
+        let mut data = StakingEvent::Deposit(deposit_event()).pack();
+        data.push(0);
+        assert!(StakingEvent::unpack(&data).is_err());
+        data.truncate(data.len() - 2);
+        assert!(StakingEvent::unpack(&data).is_err());
+        assert!(StakingEvent::unpack(&[4]).is_err());
+        assert!(StakingEvent::unpack(&[]).is_err());
+    }
+
+    #[cfg(feature = "events-parse")]
+    #[test]
+    fn parses_events_of_the_program_only() {
+        let program_id = Pubkey::new_unique();
+        let lending_program = Pubkey::new_unique();
+        let token_program = Pubkey::new_unique();
+        let data = |event: &StakingEvent| {
+            format!(
+                "{}{}",
+                PROGRAM_DATA_LOG_PREFIX,
+                base64::encode(event.pack())
+            )
+        };
+        let events = events();
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            data(&events[0]),
+            format!("Program {} invoke [2]", token_program),
+            data(&events[1]),
+            format!("Program {} success", token_program),
+            data(&events[2]),
+            format!("Program {} success", program_id),
+            format!("Program {} invoke [1]", lending_program),
+            data(&events[1]),
+            format!("Program {} invoke [2]", program_id),
+            "Program log: Instruction: ClaimReward".to_string(),
+            format!("{}not base64", PROGRAM_DATA_LOG_PREFIX),
+            data(&events[3]),
+            format!("Program {} success", program_id),
+            format!("Program {} success", lending_program),
+        ];
+
+        assert_eq!(
+            parse_events(&program_id, &logs),
+            vec![events[0].clone(), events[2].clone(), events[3].clone()]
+        );
+    }
+
+    #[cfg(feature = "events-parse")]
+    #[test]
+    fn parse_events_follows_invoke_depth() {
+        let program_id = Pubkey::new_unique();
+        let other_program = Pubkey::new_unique();
+        let event = StakingEvent::Deposit(deposit_event());
+        let data = format!(
+            "{}{}",
+            PROGRAM_DATA_LOG_PREFIX,
+            base64::encode(event.pack())
+        );
+        // the inner invocation of the staking program ends without a result line
+        let logs = vec![
+            format!("Program {} invoke [1]", other_program),
+            format!("Program {} invoke [2]", program_id),
+            format!("Program {} invoke [2]", other_program),
+            data.clone(),
+            format!("Program {} success", other_program),
+            format!("Program {} invoke [1]", program_id),
+            data,
+            format!("Program {} success", program_id),
+        ];
+
+        assert_eq!(parse_events(&program_id, &logs), vec![event]);
+    }
+}
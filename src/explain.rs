@@ -0,0 +1,501 @@
+//! Human readable descriptions of staking instructions, e.g. for reviewing multisig proposals.
+
+use solana_program::instruction::AccountMeta;
+
+use crate::instruction::StakingInstruction;
+use crate::state::{EarlyClaimPenaltyMode, TimeBasis};
+
+/// Describe `instruction` in plain text using the accounts it is sent with.
+///
+/// Amounts are scaled by `decimals` when the mint decimals are supplied and rendered in base
+/// units otherwise.
+pub fn explain(
+    instruction: &StakingInstruction,
+    accounts: &[AccountMeta],
+    decimals: Option<u8>,
+) -> String {
+    let account = |index: usize| {
+        accounts.get(index).map_or_else(
+            || format!("<missing account {}>", index),
+            |meta| meta.pubkey.to_string(),
+        )
+    };
+    match instruction {
+        StakingInstruction::InitStakingPool {
+            supply,
+            duration,
+            earliest_reward_claim_time,
+            pool_owner_authority,
+            admin_authority,
+            start_time,
+            deposit_before_start,
+            time_basis,
+            max_pool_size,
+            max_deposit_per_account,
+            ..
+        } => format!(
+            "Initialize staking pool {} distributing {} reward tokens of mint {} from {} over {} {} starting at {} {}{}, claimable from {} {}, with deposits limited to {} per pool and {} per stake account, owner {} and admin {}, authorised by {}",
+            account(3),
+            format_amount(*supply, decimals),
+            account(4),
+            account(1),
+            duration,
+            time_unit(*time_basis),
+            time_point(*time_basis),
+            start_time,
+            if *deposit_before_start {
+                " and accepting deposits before the start"
+            } else {
+                ""
+            },
+            time_point(*time_basis),
+            earliest_reward_claim_time,
+            format_limit(*max_pool_size, decimals),
+            format_limit(*max_deposit_per_account, decimals),
+            pool_owner_authority,
+            admin_authority,
+            account(0)
+        ),
+        StakingInstruction::CreateStakeAccount => format!(
+            "Create stake account {} in pool {} for owner {}",
+            account(0),
+            account(1),
+            account(2)
+        ),
+        StakingInstruction::Deposit(amount) => format!(
+            "Deposit {} into pool {} for stake account {} authorised by {}",
+            format_amount(*amount, decimals),
+            account(2),
+            account(1),
+            account(0)
+        ),
+        StakingInstruction::Withdraw(amount) => format!(
+            "Withdraw {} from pool {} for stake account {} authorised by {}",
+            format_amount(*amount, decimals),
+            account(2),
+            account(1),
+            account(0)
+        ),
+        StakingInstruction::ClaimReward => format!(
+            "Claim rewards of stake account {} in pool {} to {} authorised by {}",
+            account(1),
+            account(2),
+            account(4),
+            account(0)
+        ),
+        StakingInstruction::RefreshStakeAccount => format!(
+            "Refresh rewards of stake account {} in pool {}",
+            account(0),
+            account(1)
+        ),
+        StakingInstruction::EmergencyWithdraw => format!(
+            "Emergency withdraw the whole deposit of stake account {} from pool {}, forfeiting its rewards, authorised by {}",
+            account(1),
+            account(2),
+            account(0)
+        ),
+        StakingInstruction::WithdrawUnallocatedRewards => format!(
+            "Withdraw rewards not owed to any staker from pool {} to {} authorised by {}",
+            account(1),
+            account(3),
+            account(0)
+        ),
+        StakingInstruction::CloseStakingPool => format!(
+            "Close staking pool {} and its reward token pool {}, sending the remaining rewards to {} and the rent to {}, authorised by {}",
+            account(1),
+            account(2),
+            account(3),
+            account(4),
+            account(0)
+        ),
+        StakingInstruction::StartNewPeriod { supply, duration } => format!(
+            "Start a new reward period on pool {} distributing {} reward tokens from {} over {}, authorised by {} and {}",
+            account(4),
+            format_amount(*supply, decimals),
+            account(2),
+            duration,
+            account(0),
+            account(1)
+        ),
+        StakingInstruction::SetDepositLimits {
+            max_pool_size,
+            max_deposit_per_account,
+        } => format!(
+            "Set the deposit limits of pool {} to {} per pool and {} per stake account, authorised by {}",
+            account(1),
+            format_limit(*max_pool_size, decimals),
+            format_limit(*max_deposit_per_account, decimals),
+            account(0)
+        ),
+        StakingInstruction::SetAllowlistConfig {
+            allowlist_enabled,
+            gatekeeper,
+        } => format!(
+            "{} the allowlist of pool {} with gatekeeper {}, authorised by {}",
+            if *allowlist_enabled { "Enable" } else { "Disable" },
+            account(1),
+            gatekeeper,
+            account(0)
+        ),
+        StakingInstruction::AddToAllowlist => format!(
+            "Allow {} to stake in pool {} with allowlist entry {} paid by {}, authorised by {}",
+            account(4),
+            account(2),
+            account(3),
+            account(1),
+            account(0)
+        ),
+        StakingInstruction::RemoveFromAllowlist => format!(
+            "Remove allowlist entry {} from pool {}, sending the rent to {}, authorised by {}",
+            account(2),
+            account(1),
+            account(3),
+            account(0)
+        ),
+        StakingInstruction::MergeStakeAccounts => format!(
+            "Merge stake account {} into stake account {} in pool {}, sending the rent to {}, authorised by {}",
+            account(2),
+            account(1),
+            account(3),
+            account(4),
+            account(0)
+        ),
+        StakingInstruction::SplitStakeAccount {
+            amount,
+            reward_amount,
+        } => format!(
+            "Split {} and {} of stake account {} in pool {} into new stake account {} owned by {}, authorised by {}",
+            format_amount(*amount, decimals),
+            match reward_amount {
+                Some(reward_amount) => format!(
+                    "{} unclaimed reward tokens",
+                    format_amount(*reward_amount, decimals)
+                ),
+                None => "a proportional share of the unclaimed rewards".to_string(),
+            },
+            account(1),
+            account(3),
+            account(2),
+            account(4),
+            account(0)
+        ),
+        StakingInstruction::CompoundReward => format!(
+            "Compound the rewards of stake account {} into its deposit in pool {} authorised by {}",
+            account(1),
+            account(2),
+            account(0)
+        ),
+        StakingInstruction::InitStakeVault => format!(
+            "Make pool {} custodial with stake vault {} for mint {} paid by {}, authorised by {}",
+            account(2),
+            account(3),
+            account(4),
+            account(1),
+            account(0)
+        ),
+        StakingInstruction::SetEarlyClaimPenalty { penalty_bps, mode } => format!(
+            "Set the early claim penalty of pool {} to {}.{:02}% {}, authorised by {}",
+            account(1),
+            penalty_bps / 100,
+            penalty_bps % 100,
+            match mode {
+                EarlyClaimPenaltyMode::Redistribute => "redistributed to stakers",
+                EarlyClaimPenaltyMode::ReturnToPool => "returned to the pool",
+            },
+            account(0)
+        ),
+        StakingInstruction::SetVestingSchedule { cliff, duration } => {
+            if *duration == 0 {
+                format!(
+                    "Make the rewards of pool {} liquid on claim, authorised by {}",
+                    account(1),
+                    account(0)
+                )
+            } else {
+                format!(
+                    "Vest the claimed rewards of pool {} over {} with a cliff of {}, authorised by {}",
+                    account(1),
+                    duration,
+                    cliff,
+                    account(0)
+                )
+            }
+        }
+        StakingInstruction::CreateVestingAccount => format!(
+            "Create vesting account {} in pool {} for owner {} paid by {}",
+            account(2),
+            account(1),
+            account(3),
+            account(0)
+        ),
+        StakingInstruction::WithdrawVested => format!(
+            "Withdraw the vested rewards of vesting account {} in pool {} to {} authorised by {}",
+            account(2),
+            account(1),
+            account(4),
+            account(0)
+        ),
+        StakingInstruction::InitRewardDistributor {
+            supply,
+            duration,
+            admin_authority,
+            time_basis,
+            ..
+        } => format!(
+            "Initialize reward distributor {} emitting {} reward tokens of mint {} from {} over {} {}, with admin {}, authorised by {}",
+            account(3),
+            format_amount(*supply, decimals),
+            account(4),
+            account(1),
+            duration,
+            time_unit(*time_basis),
+            admin_authority,
+            account(0)
+        ),
+        StakingInstruction::SetDistributorWeight { weight } => format!(
+            "Set the weight of pool {} in reward distributor {} to {}, authorised by {}",
+            account(2),
+            account(1),
+            weight,
+            account(0)
+        ),
+        StakingInstruction::InitGauge {
+            epoch_duration,
+            emission_per_epoch,
+            admin_authority,
+            time_basis,
+            ..
+        } => format!(
+            "Initialize gauge {} splitting {} reward tokens of vault {} every {} {} by the votes of the stakers of pool {}, with admin {}",
+            account(0),
+            format_amount(*emission_per_epoch, decimals),
+            account(4),
+            epoch_duration,
+            time_unit(*time_basis),
+            account(1),
+            admin_authority
+        ),
+        StakingInstruction::AddGaugePool => format!(
+            "Add pool {} to gauge {} authorised by {}",
+            account(2),
+            account(1),
+            account(0)
+        ),
+        StakingInstruction::CreateGaugeVote => format!(
+            "Create gauge vote {} on gauge {} for stake account {} authorised by {} paid by {}",
+            account(3),
+            account(2),
+            account(4),
+            account(0),
+            account(1)
+        ),
+        StakingInstruction::SetGaugeVote {
+            pool_index,
+            weight_bps,
+        } => format!(
+            "Vote {}.{:02}% for gauge pool #{} with gauge vote {} of stake account {} authorised by {}",
+            weight_bps / 100,
+            weight_bps % 100,
+            pool_index,
+            account(2),
+            account(1),
+            account(0)
+        ),
+        StakingInstruction::CommitGaugeVote => format!(
+            "Count gauge vote {} of stake account {} in gauge {} authorised by {}",
+            account(2),
+            account(3),
+            account(1),
+            account(0)
+        ),
+        StakingInstruction::CrankGaugeEpoch => format!(
+            "Close the epoch of gauge {} and fund {} pools from reward token vault {}",
+            account(0),
+            accounts.len().saturating_sub(5) / 2,
+            account(3)
+        ),
+        StakingInstruction::SetMaxLockDuration { max_lock_duration } => {
+            if *max_lock_duration == 0 {
+                format!(
+                    "Disable new locks in pool {}, authorised by {}",
+                    account(1),
+                    account(0)
+                )
+            } else {
+                format!(
+                    "Set the maximum lock of pool {} to {}, authorised by {}",
+                    account(1),
+                    max_lock_duration,
+                    account(0)
+                )
+            }
+        }
+        StakingInstruction::ExtendLock { lock_end } => format!(
+            "Lock stake account {} in pool {} until {} authorised by {}",
+            account(1),
+            account(2),
+            lock_end,
+            account(0)
+        ),
+        StakingInstruction::GetVotingPower { time } => format!(
+            "Get the voting power of stake account {} in pool {} at {}",
+            account(0),
+            account(1),
+            time
+        ),
+        StakingInstruction::QueryPendingReward => format!(
+            "Query the pending reward of stake account {} in pool {}",
+            account(0),
+            account(1)
+        ),
+    }
+}
+
+fn format_limit(limit: u64, decimals: Option<u8>) -> String {
+    if limit == 0 {
+        "unlimited".to_string()
+    } else {
+        format_amount(limit, decimals)
+    }
+}
+
+fn time_unit(time_basis: TimeBasis) -> &'static str {
+    match time_basis {
+        TimeBasis::Slot => "slots",
+        TimeBasis::UnixTimestamp => "seconds",
+    }
+}
+
+fn time_point(time_basis: TimeBasis) -> &'static str {
+    match time_basis {
+        TimeBasis::Slot => "slot",
+        TimeBasis::UnixTimestamp => "unix timestamp",
+    }
+}
+
+/// Format a token amount with thousands separators, scaled by `decimals` when supplied.
+pub fn format_amount(amount: u64, decimals: Option<u8>) -> String {
+    let digits = amount.to_string();
+    let decimals = decimals.unwrap_or(0) as usize;
+    let (integer, fraction) = if digits.len() > decimals {
+        let (integer, fraction) = digits.split_at(digits.len() - decimals);
+        (integer.to_string(), fraction.to_string())
+    } else {
+        (
+            "0".to_string(),
+            format!("{:0>width$}", digits, width = decimals),
+        )
+    };
+
+    let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+    for (i, c) in integer.chars().enumerate() {
+        if i != 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    if fraction.is_empty() {
+        grouped
+    } else {
+        format!("{}.{}", grouped, fraction)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use solana_program::pubkey::Pubkey;
+
+    fn accounts(count: usize) -> (Vec<Pubkey>, Vec<AccountMeta>) {
+        let keys: Vec<Pubkey> = (0..count).map(|_| Pubkey::new_unique()).collect();
+        let metas = keys
+            .iter()
+            .map(|key| AccountMeta::new_readonly(*key, false))
+            .collect();
+        (keys, metas)
+    }
+
+    #[test]
+    fn formats_amounts() {
+        assert_eq!(format_amount(0, None), "0");
+        assert_eq!(format_amount(999, None), "999");
+        assert_eq!(format_amount(1_000, None), "1,000");
+        assert_eq!(format_amount(1_234_567, None), "1,234,567");
+        assert_eq!(format_amount(1_234_567, Some(6)), "1.234567");
+        assert_eq!(format_amount(1_234_567_890, Some(3)), "1,234,567.890");
+        assert_eq!(format_amount(42, Some(6)), "0.000042");
+        assert_eq!(
+            format_amount(u64::MAX, Some(0)),
+            "18,446,744,073,709,551,615"
+        );
+    }
+
+    #[test]
+    fn explains_init_staking_pool() {
+        let (keys, metas) = accounts(8);
+        let owner = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let instruction = StakingInstruction::InitStakingPool {
+            supply: 5_000_000,
+            duration: 86_400,
+            earliest_reward_claim_time: 1_700_000_000,
+            bump_seed_staking_program: 255,
+            pool_owner_authority: owner,
+            admin_authority: admin,
+            start_time: 1_650_000_000,
+            deposit_before_start: true,
+            time_basis: TimeBasis::UnixTimestamp,
+            max_pool_size: 0,
+            max_deposit_per_account: 1_000_000,
+        };
+
+        assert_eq!(
+            explain(&instruction, &metas, Some(3)),
+            format!(
+                "Initialize staking pool {} distributing 5,000.000 reward tokens of mint {} from {} over 86400 seconds starting at unix timestamp 1650000000 and accepting deposits before the start, claimable from unix timestamp 1700000000, with deposits limited to unlimited per pool and 1,000.000 per stake account, owner {} and admin {}, authorised by {}",
+                keys[3], keys[4], keys[1], owner, admin, keys[0]
+            )
+        );
+    }
+
+    #[test]
+    fn explains_stake_account_instructions() {
+        let (keys, metas) = accounts(5);
+
+        assert_eq!(
+            explain(&StakingInstruction::Deposit(1_500), &metas, Some(2)),
+            format!(
+                "Deposit 15.00 into pool {} for stake account {} authorised by {}",
+                keys[2], keys[1], keys[0]
+            )
+        );
+        assert_eq!(
+            explain(&StakingInstruction::Withdraw(1_500), &metas, None),
+            format!(
+                "Withdraw 1,500 from pool {} for stake account {} authorised by {}",
+                keys[2], keys[1], keys[0]
+            )
+        );
+        assert_eq!(
+            explain(&StakingInstruction::RefreshStakeAccount, &metas, None),
+            format!(
+                "Refresh rewards of stake account {} in pool {}",
+                keys[0], keys[1]
+            )
+        );
+    }
+
+    #[test]
+    fn explains_missing_accounts() {
+        let (keys, metas) = accounts(2);
+
+        assert_eq!(
+            explain(&StakingInstruction::ClaimReward, &metas, None),
+            format!(
+                "Claim rewards of stake account {} in pool <missing account 2> to <missing account 4> authorised by {}",
+                keys[1], keys[0]
+            )
+        );
+    }
+}
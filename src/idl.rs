@@ -0,0 +1,909 @@
+//! Machine readable description of the staking program interface.
+//!
+//! `INSTRUCTIONS` and `ACCOUNTS` are the single source of truth for the wire layouts
+//! produced by `StakingInstruction::pack` and the `Pack` implementations of the state
+//! accounts; `to_json` renders them, together with the `StakingError` codes, as a JSON IDL.
+
+use std::fmt::Write;
+
+use solana_maths::Decimal;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::PUBKEY_BYTES;
+
+use crate::error::StakingError;
+use crate::state::{
+    AllowlistEntry, Gauge, GaugeVote, RewardDistributor, StakeAccount, StakingPool, VestingAccount,
+    MAX_DISTRIBUTOR_POOLS, MAX_GAUGE_POOLS,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IdlType {
+    U8,
+    U16,
+    U64,
+    Bool,
+    /// `bool` presence flag followed by a `u64`, zero when absent.
+    OptionU64,
+    PublicKey,
+    /// `u128` little endian, scaled by 10^18.
+    Decimal,
+    /// Fixed number of entries of a type from `TYPES`.
+    Array(&'static IdlAccountType, usize),
+    /// Reserved bytes.
+    Padding(usize),
+}
+
+impl IdlType {
+    pub fn size(&self) -> usize {
+        match self {
+            IdlType::U8 => 1,
+            IdlType::U16 => 2,
+            IdlType::U64 => 8,
+            IdlType::Bool => 1,
+            IdlType::OptionU64 => 1 + 8,
+            IdlType::PublicKey => PUBKEY_BYTES,
+            IdlType::Decimal => Decimal::LEN,
+            IdlType::Array(ty, len) => ty.size * len,
+            IdlType::Padding(len) => *len,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            IdlType::U8 => "u8".to_string(),
+            IdlType::U16 => "u16".to_string(),
+            IdlType::U64 => "u64".to_string(),
+            IdlType::Bool => "bool".to_string(),
+            IdlType::OptionU64 => "option<u64>".to_string(),
+            IdlType::PublicKey => "publicKey".to_string(),
+            IdlType::Decimal => "decimal".to_string(),
+            IdlType::Array(ty, len) => format!("[{}; {}]", ty.name, len),
+            IdlType::Padding(len) => format!("[u8; {}]", len),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IdlField {
+    pub name: &'static str,
+    pub ty: IdlType,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IdlAccountMeta {
+    pub name: &'static str,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    pub is_optional: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IdlInstruction {
+    pub name: &'static str,
+    pub tag: u8,
+    pub args: &'static [IdlField],
+    pub accounts: &'static [IdlAccountMeta],
+}
+
+impl IdlInstruction {
+    /// Length of the packed instruction data, including the tag.
+    pub fn data_len(&self) -> usize {
+        1 + self.args.iter().map(|arg| arg.ty.size()).sum::<usize>()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IdlAccountType {
+    pub name: &'static str,
+    pub size: usize,
+    pub fields: &'static [IdlField],
+}
+
+impl IdlAccountType {
+    /// Fields paired with their byte offset in the packed account.
+    pub fn field_offsets(&self) -> Vec<(IdlField, usize)> {
+        let mut offset = 0;
+        self.fields
+            .iter()
+            .map(|field| {
+                let field_offset = offset;
+                offset += field.ty.size();
+                (*field, field_offset)
+            })
+            .collect()
+    }
+}
+
+const fn field(name: &'static str, ty: IdlType) -> IdlField {
+    IdlField { name, ty }
+}
+
+const fn account(name: &'static str, is_signer: bool, is_writable: bool) -> IdlAccountMeta {
+    IdlAccountMeta {
+        name,
+        is_signer,
+        is_writable,
+        is_optional: false,
+    }
+}
+
+const fn optional_account(
+    name: &'static str,
+    is_signer: bool,
+    is_writable: bool,
+) -> IdlAccountMeta {
+    IdlAccountMeta {
+        name,
+        is_signer,
+        is_writable,
+        is_optional: true,
+    }
+}
+
+pub const INSTRUCTIONS: &[IdlInstruction] = &[
+    IdlInstruction {
+        name: "InitStakingPool",
+        tag: 0,
+        args: &[
+            field("supply", IdlType::U64),
+            field("duration", IdlType::U64),
+            field("earliest_reward_claim_time", IdlType::U64),
+            field("bump_seed_staking_program", IdlType::U8),
+            field("pool_owner_authority", IdlType::PublicKey),
+            field("admin_authority", IdlType::PublicKey),
+            field("start_time", IdlType::U64),
+            field("deposit_before_start", IdlType::Bool),
+            field("time_basis", IdlType::U8),
+            field("max_pool_size", IdlType::U64),
+            field("max_deposit_per_account", IdlType::U64),
+        ],
+        accounts: &[
+            account("transfer_reward_token_authority", true, false),
+            account("reward_token_supply", false, true),
+            account("reward_token_pool", false, true),
+            account("staking_pool", false, true),
+            account("reward_token_mint", false, false),
+            account("staking_program_derived", false, false),
+            account("rent", false, false),
+            account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "CreateStakeAccount",
+        tag: 1,
+        args: &[],
+        accounts: &[
+            account("stake_account", false, true),
+            account("staking_pool", false, false),
+            account("stake_account_owner", false, false),
+            account("rent", false, false),
+            optional_account("gatekeeper_or_allowlist_entry", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "Deposit",
+        tag: 2,
+        args: &[field("amount", IdlType::U64)],
+        accounts: &[
+            account("authority", true, false),
+            account("stake_account", false, true),
+            account("staking_pool", false, true),
+            account("clock", false, false),
+            optional_account("source", false, true),
+            optional_account("stake_vault", false, true),
+            optional_account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "Withdraw",
+        tag: 3,
+        args: &[field("amount", IdlType::U64)],
+        accounts: &[
+            account("authority", true, false),
+            account("stake_account", false, true),
+            account("staking_pool", false, true),
+            account("clock", false, false),
+            optional_account("stake_vault", false, true),
+            optional_account("destination", false, true),
+            optional_account("staking_program_derived", false, false),
+            optional_account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "ClaimReward",
+        tag: 4,
+        args: &[],
+        accounts: &[
+            account("stake_account_owner", true, false),
+            account("stake_account", false, true),
+            account("staking_pool", false, true),
+            account("reward_token_pool", false, true),
+            account("reward_destination", false, true),
+            account("staking_program_derived", false, false),
+            account("clock", false, false),
+            account("token_program", false, false),
+            optional_account("sub_reward_token_pool", false, true),
+            optional_account("sub_reward_destination", false, true),
+        ],
+    },
+    IdlInstruction {
+        name: "RefreshStakeAccount",
+        tag: 5,
+        args: &[],
+        accounts: &[
+            account("stake_account", false, true),
+            account("staking_pool", false, true),
+            account("clock", false, false),
+            optional_account("reward_distributor", false, true),
+            optional_account("reward_distributor_vault", false, true),
+            optional_account("reward_distributor_derived", false, false),
+            optional_account("reward_token_pool", false, true),
+            optional_account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "EmergencyWithdraw",
+        tag: 6,
+        args: &[],
+        accounts: &[
+            account("authority", true, false),
+            account("stake_account", false, true),
+            account("staking_pool", false, true),
+            account("clock", false, false),
+            optional_account("stake_vault", false, true),
+            optional_account("destination", false, true),
+            optional_account("staking_program_derived", false, false),
+            optional_account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "WithdrawUnallocatedRewards",
+        tag: 7,
+        args: &[],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("staking_pool", false, true),
+            account("reward_token_pool", false, true),
+            account("destination", false, true),
+            account("staking_program_derived", false, false),
+            account("clock", false, false),
+            account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "CloseStakingPool",
+        tag: 8,
+        args: &[],
+        accounts: &[
+            account("owner_authority", true, false),
+            account("staking_pool", false, true),
+            account("reward_token_pool", false, true),
+            account("reward_destination", false, true),
+            account("destination", false, true),
+            account("staking_program_derived", false, false),
+            account("clock", false, false),
+            account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "StartNewPeriod",
+        tag: 9,
+        args: &[
+            field("supply", IdlType::U64),
+            field("duration", IdlType::U64),
+        ],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("transfer_reward_token_authority", true, false),
+            account("reward_token_supply", false, true),
+            account("reward_token_pool", false, true),
+            account("staking_pool", false, true),
+            account("clock", false, false),
+            account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "SetDepositLimits",
+        tag: 10,
+        args: &[
+            field("max_pool_size", IdlType::U64),
+            field("max_deposit_per_account", IdlType::U64),
+        ],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("staking_pool", false, true),
+        ],
+    },
+    IdlInstruction {
+        name: "SetAllowlistConfig",
+        tag: 11,
+        args: &[
+            field("allowlist_enabled", IdlType::Bool),
+            field("gatekeeper", IdlType::PublicKey),
+        ],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("staking_pool", false, true),
+        ],
+    },
+    IdlInstruction {
+        name: "AddToAllowlist",
+        tag: 12,
+        args: &[],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("payer", true, true),
+            account("staking_pool", false, false),
+            account("allowlist_entry", false, true),
+            account("owner", false, false),
+            account("system_program", false, false),
+            account("rent", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "RemoveFromAllowlist",
+        tag: 13,
+        args: &[],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("staking_pool", false, false),
+            account("allowlist_entry", false, true),
+            account("destination", false, true),
+        ],
+    },
+    IdlInstruction {
+        name: "MergeStakeAccounts",
+        tag: 14,
+        args: &[],
+        accounts: &[
+            account("stake_account_owner", true, false),
+            account("destination_stake_account", false, true),
+            account("source_stake_account", false, true),
+            account("staking_pool", false, true),
+            account("rent_destination", false, true),
+            account("clock", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "SplitStakeAccount",
+        tag: 15,
+        args: &[
+            field("amount", IdlType::U64),
+            field("reward_amount", IdlType::OptionU64),
+        ],
+        accounts: &[
+            account("stake_account_owner", true, false),
+            account("stake_account", false, true),
+            account("new_stake_account", false, true),
+            account("staking_pool", false, true),
+            account("new_stake_account_owner", false, false),
+            account("clock", false, false),
+            account("rent", false, false),
+            optional_account("gatekeeper_or_allowlist_entry", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "CompoundReward",
+        tag: 16,
+        args: &[],
+        accounts: &[
+            account("authority", true, false),
+            account("stake_account", false, true),
+            account("staking_pool", false, true),
+            account("clock", false, false),
+            account("reward_token_pool", false, true),
+            account("stake_vault", false, true),
+            account("staking_program_derived", false, false),
+            account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "InitStakeVault",
+        tag: 17,
+        args: &[],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("payer", true, true),
+            account("staking_pool", false, true),
+            account("stake_vault", false, true),
+            account("stake_mint", false, false),
+            account("staking_program_derived", false, false),
+            account("system_program", false, false),
+            account("token_program", false, false),
+            account("rent", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "SetEarlyClaimPenalty",
+        tag: 18,
+        args: &[
+            field("penalty_bps", IdlType::U16),
+            field("mode", IdlType::U8),
+        ],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("staking_pool", false, true),
+        ],
+    },
+    IdlInstruction {
+        name: "SetVestingSchedule",
+        tag: 19,
+        args: &[
+            field("cliff", IdlType::U64),
+            field("duration", IdlType::U64),
+        ],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("staking_pool", false, true),
+        ],
+    },
+    IdlInstruction {
+        name: "CreateVestingAccount",
+        tag: 20,
+        args: &[],
+        accounts: &[
+            account("payer", true, true),
+            account("staking_pool", false, false),
+            account("vesting_account", false, true),
+            account("owner", false, false),
+            account("system_program", false, false),
+            account("rent", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "WithdrawVested",
+        tag: 21,
+        args: &[],
+        accounts: &[
+            account("owner", true, false),
+            account("staking_pool", false, true),
+            account("vesting_account", false, true),
+            account("reward_token_pool", false, true),
+            account("reward_destination", false, true),
+            account("staking_program_derived", false, false),
+            account("clock", false, false),
+            account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "InitRewardDistributor",
+        tag: 22,
+        args: &[
+            field("supply", IdlType::U64),
+            field("duration", IdlType::U64),
+            field("bump_seed", IdlType::U8),
+            field("admin_authority", IdlType::PublicKey),
+            field("time_basis", IdlType::U8),
+        ],
+        accounts: &[
+            account("transfer_reward_token_authority", true, false),
+            account("reward_token_supply", false, true),
+            account("reward_distributor_vault", false, true),
+            account("reward_distributor", false, true),
+            account("reward_token_mint", false, false),
+            account("reward_distributor_derived", false, false),
+            account("clock", false, false),
+            account("rent", false, false),
+            account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "SetDistributorWeight",
+        tag: 23,
+        args: &[field("weight", IdlType::U64)],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("reward_distributor", false, true),
+            account("staking_pool", false, false),
+            account("clock", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "InitGauge",
+        tag: 24,
+        args: &[
+            field("epoch_duration", IdlType::U64),
+            field("emission_per_epoch", IdlType::U64),
+            field("bump_seed", IdlType::U8),
+            field("admin_authority", IdlType::PublicKey),
+            field("time_basis", IdlType::U8),
+        ],
+        accounts: &[
+            account("gauge", false, true),
+            account("governance_pool", false, false),
+            account("clock", false, false),
+            account("rent", false, false),
+            account("reward_token_vault", false, true),
+            account("reward_token_mint", false, false),
+            account("gauge_derived", false, false),
+            account("token_program", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "AddGaugePool",
+        tag: 25,
+        args: &[],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("gauge", false, true),
+            account("staking_pool", false, false),
+            account("reward_token_pool", false, false),
+            account("reward_token_vault", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "CreateGaugeVote",
+        tag: 26,
+        args: &[],
+        accounts: &[
+            account("stake_account_owner", true, false),
+            account("payer", true, true),
+            account("gauge", false, false),
+            account("gauge_vote", false, true),
+            account("stake_account", false, false),
+            account("system_program", false, false),
+            account("rent", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "SetGaugeVote",
+        tag: 27,
+        args: &[
+            field("pool_index", IdlType::U8),
+            field("weight_bps", IdlType::U16),
+        ],
+        accounts: &[
+            account("stake_account_owner", true, false),
+            account("stake_account", false, false),
+            account("gauge_vote", false, true),
+        ],
+    },
+    IdlInstruction {
+        name: "CommitGaugeVote",
+        tag: 28,
+        args: &[],
+        accounts: &[
+            account("stake_account_owner", true, false),
+            account("gauge", false, true),
+            account("gauge_vote", false, true),
+            account("stake_account", false, true),
+            account("governance_pool", false, false),
+            account("clock", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "CrankGaugeEpoch",
+        tag: 29,
+        args: &[],
+        accounts: &[
+            account("gauge", false, true),
+            account("gauge_derived", false, false),
+            account("clock", false, false),
+            account("reward_token_vault", false, true),
+            account("token_program", false, false),
+            optional_account("staking_pools", false, true),
+            optional_account("reward_token_pools", false, true),
+        ],
+    },
+    IdlInstruction {
+        name: "SetMaxLockDuration",
+        tag: 30,
+        args: &[field("max_lock_duration", IdlType::U64)],
+        accounts: &[
+            account("admin_authority", true, false),
+            account("staking_pool", false, true),
+        ],
+    },
+    IdlInstruction {
+        name: "ExtendLock",
+        tag: 31,
+        args: &[field("lock_end", IdlType::U64)],
+        accounts: &[
+            account("stake_account_owner", true, false),
+            account("stake_account", false, true),
+            account("staking_pool", false, false),
+            account("clock", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "GetVotingPower",
+        tag: 32,
+        args: &[field("time", IdlType::U64)],
+        accounts: &[
+            account("stake_account", false, false),
+            account("staking_pool", false, false),
+            account("clock", false, false),
+        ],
+    },
+    IdlInstruction {
+        name: "QueryPendingReward",
+        tag: 33,
+        args: &[],
+        accounts: &[
+            account("stake_account", false, false),
+            account("staking_pool", false, false),
+            account("clock", false, false),
+        ],
+    },
+];
+
+const DISTRIBUTOR_POOL: IdlAccountType = IdlAccountType {
+    name: "DistributorPool",
+    size: PUBKEY_BYTES + 8 + Decimal::LEN + Decimal::LEN,
+    fields: &[
+        field("staking_pool", IdlType::PublicKey),
+        field("weight", IdlType::U64),
+        field("checkpoint", IdlType::Decimal),
+        field("accrued_reward_wads", IdlType::Decimal),
+    ],
+};
+
+const GAUGE_POOL: IdlAccountType = IdlAccountType {
+    name: "GaugePool",
+    size: PUBKEY_BYTES + 8,
+    fields: &[
+        field("staking_pool", IdlType::PublicKey),
+        field("votes", IdlType::U64),
+    ],
+};
+
+const GAUGE_WEIGHT: IdlAccountType = IdlAccountType {
+    name: "GaugeWeight",
+    size: 2,
+    fields: &[field("weight_bps", IdlType::U16)],
+};
+
+/// Types used by the fields of `ACCOUNTS`.
+pub const TYPES: &[IdlAccountType] = &[DISTRIBUTOR_POOL, GAUGE_POOL, GAUGE_WEIGHT];
+
+pub const ACCOUNTS: &[IdlAccountType] = &[
+    IdlAccountType {
+        name: "StakingPool",
+        size: StakingPool::LEN,
+        fields: &[
+            field("version", IdlType::U8),
+            field("owner_authority", IdlType::PublicKey),
+            field("admin_authority", IdlType::PublicKey),
+            field("reward_token_pool", IdlType::PublicKey),
+            field("last_update", IdlType::U64),
+            field("end_time", IdlType::U64),
+            field("duration", IdlType::U64),
+            field("earliest_reward_claim_time", IdlType::U64),
+            field("rate_per_slot", IdlType::Decimal),
+            field("cumulative_rate", IdlType::Decimal),
+            field("pool_size", IdlType::U64),
+            field("bump_seed_staking_program", IdlType::U8),
+            field("unallocated_reward_wads", IdlType::Decimal),
+            field("start_time", IdlType::U64),
+            field("deposit_before_start", IdlType::Bool),
+            field("time_basis", IdlType::U8),
+            field("max_pool_size", IdlType::U64),
+            field("max_deposit_per_account", IdlType::U64),
+            field("allowlist_enabled", IdlType::Bool),
+            field("gatekeeper", IdlType::PublicKey),
+            field("custodial", IdlType::Bool),
+            field("early_claim_penalty_bps", IdlType::U16),
+            field("early_claim_penalty_mode", IdlType::U8),
+            field("vesting_cliff", IdlType::U64),
+            field("vesting_duration", IdlType::U64),
+            field("max_lock_duration", IdlType::U64),
+            field("owed_reward_wads", IdlType::Decimal),
+            field("owed_reward_tracked", IdlType::Bool),
+            field("reserved", IdlType::Padding(8)),
+        ],
+    },
+    IdlAccountType {
+        name: "StakeAccount",
+        size: StakeAccount::LEN,
+        fields: &[
+            field("version", IdlType::U8),
+            field("start_rate", IdlType::Decimal),
+            field("owner", IdlType::PublicKey),
+            field("pool_pubkey", IdlType::PublicKey),
+            field("deposited_amount", IdlType::U64),
+            field("unclaimed_reward_wads", IdlType::Decimal),
+            field("lock_end", IdlType::U64),
+            field("voted_until", IdlType::U64),
+            field("reserved", IdlType::Padding(112)),
+        ],
+    },
+    IdlAccountType {
+        name: "AllowlistEntry",
+        size: AllowlistEntry::LEN,
+        fields: &[
+            field("version", IdlType::U8),
+            field("staking_pool", IdlType::PublicKey),
+            field("owner", IdlType::PublicKey),
+            field("bump_seed", IdlType::U8),
+            field("reserved", IdlType::Padding(32)),
+        ],
+    },
+    IdlAccountType {
+        name: "VestingAccount",
+        size: VestingAccount::LEN,
+        fields: &[
+            field("version", IdlType::U8),
+            field("staking_pool", IdlType::PublicKey),
+            field("owner", IdlType::PublicKey),
+            field("bump_seed", IdlType::U8),
+            field("vesting_start", IdlType::U64),
+            field("total_amount", IdlType::U64),
+            field("released_amount", IdlType::U64),
+            field("unlocked_amount", IdlType::U64),
+            field("cliff_start", IdlType::U64),
+            field("reserved", IdlType::Padding(24)),
+        ],
+    },
+    IdlAccountType {
+        name: "RewardDistributor",
+        size: RewardDistributor::LEN,
+        fields: &[
+            field("version", IdlType::U8),
+            field("admin_authority", IdlType::PublicKey),
+            field("reward_token_vault", IdlType::PublicKey),
+            field("bump_seed", IdlType::U8),
+            field("time_basis", IdlType::U8),
+            field("rate_per_slot", IdlType::Decimal),
+            field("reward_per_weight", IdlType::Decimal),
+            field("unallocated_reward_wads", IdlType::Decimal),
+            field("last_update", IdlType::U64),
+            field("end_time", IdlType::U64),
+            field("total_weight", IdlType::U64),
+            field("pools_len", IdlType::U8),
+            field(
+                "pools",
+                IdlType::Array(&DISTRIBUTOR_POOL, MAX_DISTRIBUTOR_POOLS),
+            ),
+            field("reserved", IdlType::Padding(64)),
+        ],
+    },
+    IdlAccountType {
+        name: "Gauge",
+        size: Gauge::LEN,
+        fields: &[
+            field("version", IdlType::U8),
+            field("admin_authority", IdlType::PublicKey),
+            field("governance_pool", IdlType::PublicKey),
+            field("reward_token_vault", IdlType::PublicKey),
+            field("bump_seed", IdlType::U8),
+            field("time_basis", IdlType::U8),
+            field("epoch_duration", IdlType::U64),
+            field("epoch", IdlType::U64),
+            field("epoch_start", IdlType::U64),
+            field("emission_per_epoch", IdlType::U64),
+            field("pools_len", IdlType::U8),
+            field("pools", IdlType::Array(&GAUGE_POOL, MAX_GAUGE_POOLS)),
+            field("reserved", IdlType::Padding(32)),
+        ],
+    },
+    IdlAccountType {
+        name: "GaugeVote",
+        size: GaugeVote::LEN,
+        fields: &[
+            field("version", IdlType::U8),
+            field("gauge", IdlType::PublicKey),
+            field("stake_account", IdlType::PublicKey),
+            field("bump_seed", IdlType::U8),
+            field("committed_epoch", IdlType::U64),
+            field(
+                "weights_bps",
+                IdlType::Array(&GAUGE_WEIGHT, MAX_GAUGE_POOLS),
+            ),
+            field("reserved", IdlType::Padding(32)),
+        ],
+    },
+];
+
+/// Every `StakingError` with its custom program error code.
+pub fn errors() -> Vec<(u32, StakingError)> {
+    (0..)
+        .map(|code| StakingError::from_code(code).map(|error| (code, error)))
+        .take_while(Option::is_some)
+        .flatten()
+        .collect()
+}
+
+/// Render the IDL as a JSON document.
+pub fn to_json() -> String {
+    let mut json = String::new();
+    json.push_str("{\n");
+    let _ = writeln!(json, "  \"name\": \"port_staking\",");
+    let _ = writeln!(json, "  \"version\": \"{}\",", env!("CARGO_PKG_VERSION"));
+
+    json.push_str("  \"instructions\": [\n");
+    let instructions: Vec<String> = INSTRUCTIONS
+        .iter()
+        .map(|ix| {
+            let accounts: Vec<String> = ix
+                .accounts
+                .iter()
+                .map(|meta| {
+                    format!(
+                        "        {{ \"name\": \"{}\", \"isSigner\": {}, \"isMut\": {}, \"isOptional\": {} }}",
+                        meta.name, meta.is_signer, meta.is_writable, meta.is_optional
+                    )
+                })
+                .collect();
+            format!(
+                "    {{\n      \"name\": \"{}\",\n      \"tag\": {},\n      \"args\": [{}],\n      \"accounts\": [\n{}\n      ]\n    }}",
+                ix.name,
+                ix.tag,
+                render_fields(ix.args.iter().map(|arg| (*arg, None))),
+                accounts.join(",\n")
+            )
+        })
+        .collect();
+    json.push_str(&instructions.join(",\n"));
+    json.push_str("\n  ],\n");
+
+    json.push_str("  \"types\": [\n");
+    json.push_str(&render_account_types(TYPES));
+    json.push_str("\n  ],\n");
+
+    json.push_str("  \"accounts\": [\n");
+    json.push_str(&render_account_types(ACCOUNTS));
+    json.push_str("\n  ],\n");
+
+    json.push_str("  \"errors\": [\n");
+    let errors: Vec<String> = errors()
+        .into_iter()
+        .map(|(code, error)| {
+            format!(
+                "    {{ \"code\": {}, \"name\": \"{:?}\", \"msg\": \"{}\" }}",
+                code,
+                error,
+                escape(&error.to_string())
+            )
+        })
+        .collect();
+    json.push_str(&errors.join(",\n"));
+    json.push_str("\n  ]\n}\n");
+    json
+}
+
+fn render_account_types(types: &[IdlAccountType]) -> String {
+    let rendered: Vec<String> = types
+        .iter()
+        .map(|account| {
+            format!(
+                "    {{\n      \"name\": \"{}\",\n      \"size\": {},\n      \"fields\": [{}]\n    }}",
+                account.name,
+                account.size,
+                render_fields(
+                    account
+                        .field_offsets()
+                        .into_iter()
+                        .map(|(field, offset)| (field, Some(offset)))
+                )
+            )
+        })
+        .collect();
+    rendered.join(",\n")
+}
+
+fn render_fields(fields: impl Iterator<Item = (IdlField, Option<usize>)>) -> String {
+    let rendered: Vec<String> = fields
+        .map(|(field, offset)| match offset {
+            Some(offset) => format!(
+                "\n        {{ \"name\": \"{}\", \"type\": \"{}\", \"offset\": {} }}",
+                field.name,
+                field.ty.name(),
+                offset
+            ),
+            None => format!(
+                "\n        {{ \"name\": \"{}\", \"type\": \"{}\" }}",
+                field.name,
+                field.ty.name()
+            ),
+        })
+        .collect();
+    if rendered.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n      ", rendered.join(","))
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -1,5 +1,5 @@
 use solana_program::clock::Slot;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use std::mem::size_of;
 
 use solana_program::instruction::{AccountMeta, Instruction};
@@ -8,10 +8,26 @@ use solana_program::pubkey::PUBKEY_BYTES;
 use crate::error::StakingError;
 use crate::instruction::StakingInstruction::*;
 use crate::solana_program::{msg, program_error::ProgramError, pubkey::Pubkey, sysvar};
+use crate::state::{
+    find_allowlist_entry_address, find_gauge_vote_address, find_stake_vault_address,
+    find_vesting_account_address, EarlyClaimPenaltyMode, TimeBasis,
+};
 
 /// Instructions supported by the lending program.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
 pub enum StakingInstruction {
+    /// Initialize a staking pool, setting `owed_reward_tracked` as it tracks the rewards owed to
+    /// its stakers from the start.
+    ///
+    /// The groups of fields added since the first layout, the start time, the time basis and
+    /// the deposit limits, are optional from the end and read as their defaults when left out.
+    /// `pack` leaves out the trailing groups at their defaults.
+    ///
     /// Accounts expected by this instruction:
     ///   0. `[signer]` Transfer reward token authority.
     ///   1. `[writable]` Reward token supply.
@@ -26,8 +42,15 @@ pub enum StakingInstruction {
         duration: u64, // num of slots
         earliest_reward_claim_time: Slot,
         bump_seed_staking_program: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
         pool_owner_authority: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
         admin_authority: Pubkey,
+        start_time: Slot, // reward accrual starts here, 0 to start immediately
+        deposit_before_start: bool,
+        time_basis: TimeBasis,        // unit of every time above
+        max_pool_size: u64,           // 0 for no limit
+        max_deposit_per_account: u64, // 0 for no limit
     },
     /// Accounts expected by this instruction:
     ///
@@ -35,26 +58,50 @@ pub enum StakingInstruction {
     ///   1. `[]` Staking Pool.
     ///   2. `[]` Stake account owner.
     ///   3. `[]` Rent sysvar.
+    ///
+    /// Optional account expected when the staking pool has its allowlist enabled, either:
+    ///   4. `[signer]` Staking pool gatekeeper.
+    ///   4. `[]` Allowlist entry of the stake account owner.
     CreateStakeAccount,
-    /// Deposit to a stake account.
+    /// Deposit to a stake account, within the deposit limits of the staking pool.
+    /// Sets the `StakeBalance` return data.
     ///
     /// Accounts expected by this instruction:
     ///   0. `[signer]` authority.
     ///   1. `[writable]` Stake account.
     ///   2. `[writable]` Staking pool.
     ///   3. `[]` Clock sysvar.
+    ///
+    /// Additional accounts expected when the staking pool is custodial:
+    ///   4. `[writable]` Source token account, owned by the authority.
+    ///   5. `[writable]` Stake vault.
+    ///   6. `[]` Token program.
     Deposit(u64),
 
     /// Withdrawn to a stake account.
     ///
+    /// Fails before the lock end of the stake account, or the end of a gauge epoch it voted in.
+    /// Sets the `StakeBalance` return data.
+    ///
     /// Accounts expected by this instruction:
     ///   0. `[signer]` authority.
     ///   1. `[writable]` Stake account.
     ///   2. `[writable]` Staking pool.
     ///   3. `[]` Clock sysvar.
+    ///
+    /// Additional accounts expected when the staking pool is custodial:
+    ///   4. `[writable]` Stake vault.
+    ///   5. `[writable]` Destination token account.
+    ///   6. `[]` Staking program derived that owns the stake vault.
+    ///   7. `[]` Token program.
     Withdraw(u64),
     /// Claim all unclaimed Reward from a stake account
     ///
+    /// Before the earliest reward claim time, pays out the rewards less the early claim penalty
+    /// of the staking pool, or fails when the pool has none. When the staking pool vests rewards,
+    /// the reward destination is the vesting account of the owner, topped up instead of
+    /// transferred to. Sets the `ClaimedReward` return data.
+    ///
     /// Accounts expected by this instruction:
     ///   0. `[signer]` Stake account owner.
     ///   1. `[writable]` Stake account.
@@ -65,6 +112,374 @@ pub enum StakingInstruction {
     ///   6. `[]` Clock sysvar.
     ///   7. `[]` Token program.
     ClaimReward,
+    /// Settle the pending reward of a stake account into `unclaimed_reward_wads`
+    /// without claiming it.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[writable]` Stake account.
+    ///   1. `[writable]` Staking pool.
+    ///   2. `[]` Clock sysvar.
+    ///
+    /// Additional accounts expected when the staking pool is fed by a reward distributor, which
+    /// first pulls the share of the pool into its reward token pool. No other instruction pulls,
+    /// so clients of a fed pool send this one first in transactions that settle stake accounts,
+    /// such as `Deposit`, `Withdraw` or `ClaimReward`, or the share not yet pulled is shared
+    /// later among the stake at that time:
+    ///   3. `[writable]` Reward distributor.
+    ///   4. `[writable]` Reward distributor token vault.
+    ///   5. `[]` Reward distributor derived that owns the token vault.
+    ///   6. `[writable]` Reward token pool.
+    ///   7. `[]` Token program.
+    RefreshStakeAccount,
+    /// Withdraw the whole deposit of a stake account, forfeiting its unclaimed rewards.
+    ///
+    /// Does not touch any reward token account, so it keeps working when claiming does not, and
+    /// skips the rewards accrued since the last pool update when they cannot be settled. Fails
+    /// before the lock end of the stake account, but not while it counts in a gauge epoch.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` authority.
+    ///   1. `[writable]` Stake account.
+    ///   2. `[writable]` Staking pool.
+    ///   3. `[]` Clock sysvar.
+    ///
+    /// Additional accounts expected when the staking pool is custodial:
+    ///   4. `[writable]` Stake vault.
+    ///   5. `[writable]` Destination token account.
+    ///   6. `[]` Staking program derived that owns the stake vault.
+    ///   7. `[]` Token program.
+    EmergencyWithdraw,
+    /// Transfer rewards not owed to any staker out of the reward token pool once the reward
+    /// period has ended: the balance of the reward token pool beyond `owed_reward_wads` rounded
+    /// up, including rounding dust and tokens sent to the reward token pool directly. Fails for
+    /// staking pools without `owed_reward_tracked`, created before owed rewards were tracked.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool admin authority.
+    ///   1. `[writable]` Staking pool.
+    ///   2. `[writable]` Reward token pool.
+    ///   3. `[writable]` Destination token account.
+    ///   4. `[]` Staking Pool owner derived from staking pool pubkey
+    ///   5. `[]` Clock sysvar.
+    ///   6. `[]` Token program.
+    WithdrawUnallocatedRewards,
+    /// Close a finished staking pool with no deposits left, closing its reward token pool
+    /// and sending the rent of both accounts to the destination.
+    ///
+    /// Fails while a whole token of rewards is still owed to stakers, and for staking pools
+    /// without `owed_reward_tracked`, created before owed rewards were tracked. The rest of the
+    /// reward token pool, rounding dust once `WithdrawUnallocatedRewards` ran, is transferred to
+    /// the reward destination, a token account of the staking pool admin authority.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool owner authority.
+    ///   1. `[writable]` Staking pool.
+    ///   2. `[writable]` Reward token pool.
+    ///   3. `[writable]` Reward destination.
+    ///   4. `[writable]` Lamports destination.
+    ///   5. `[]` Staking Pool owner derived from staking pool pubkey
+    ///   6. `[]` Clock sysvar.
+    ///   7. `[]` Token program.
+    CloseStakingPool,
+    /// Start a new reward period on an ended staking pool, funding it with `supply` reward
+    /// tokens emitted over `duration`.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool admin authority.
+    ///   1. `[signer]` Transfer reward token authority.
+    ///   2. `[writable]` Reward token supply.
+    ///   3. `[writable]` Reward token pool.
+    ///   4. `[writable]` Staking pool.
+    ///   5. `[]` Clock sysvar.
+    ///   6. `[]` Token program.
+    StartNewPeriod { supply: u64, duration: u64 },
+    /// Set the deposit limits of a staking pool, 0 for no limit. Deposits already made above
+    /// a lowered limit are kept.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool admin authority.
+    ///   1. `[writable]` Staking pool.
+    SetDepositLimits {
+        max_pool_size: u64,
+        max_deposit_per_account: u64,
+    },
+    /// Enable or disable the allowlist of a staking pool and set its gatekeeper, the default
+    /// pubkey for none.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool admin authority.
+    ///   1. `[writable]` Staking pool.
+    SetAllowlistConfig {
+        allowlist_enabled: bool,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+        gatekeeper: Pubkey,
+    },
+    /// Allow an owner to create stake accounts in an allowlisted staking pool.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool admin authority.
+    ///   1. `[signer, writable]` Payer of the allowlist entry rent.
+    ///   2. `[]` Staking pool.
+    ///   3. `[writable]` Allowlist entry - uninitialized, derived from the staking pool and owner.
+    ///   4. `[]` Owner to allow.
+    ///   5. `[]` System program.
+    ///   6. `[]` Rent sysvar.
+    AddToAllowlist,
+    /// Close the allowlist entry of an owner. Stake accounts already created are kept.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool admin authority.
+    ///   1. `[]` Staking pool.
+    ///   2. `[writable]` Allowlist entry.
+    ///   3. `[writable]` Destination of the allowlist entry rent.
+    RemoveFromAllowlist,
+    /// Merge a source stake account into a destination stake account of the same owner in the
+    /// same staking pool, settling the rewards of both, then close the source. Fails before the
+    /// end of a gauge epoch the source voted in.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Stake account owner.
+    ///   1. `[writable]` Destination stake account.
+    ///   2. `[writable]` Source stake account.
+    ///   3. `[writable]` Staking pool.
+    ///   4. `[writable]` Destination of the source stake account rent.
+    ///   5. `[]` Clock sysvar.
+    MergeStakeAccounts,
+    /// Split `amount` of the deposit of a stake account into a new stake account of the same
+    /// staking pool, possibly of another owner, with `reward_amount` of the unclaimed rewards or
+    /// a share proportional to `amount` when `None`. Fails before the end of a gauge epoch the
+    /// source voted in.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Source stake account owner.
+    ///   1. `[writable]` Source stake account.
+    ///   2. `[writable]` New stake account - uninitialized.
+    ///   3. `[writable]` Staking pool.
+    ///   4. `[]` New stake account owner.
+    ///   5. `[]` Clock sysvar.
+    ///   6. `[]` Rent sysvar.
+    ///
+    /// Optional account expected when the staking pool has its allowlist enabled, as for
+    /// `CreateStakeAccount` with the new stake account owner.
+    SplitStakeAccount {
+        amount: u64,
+        reward_amount: Option<u64>,
+    },
+    /// Compound the unclaimed rewards of a stake account into its deposit, moving the reward
+    /// tokens from the reward token pool to the stake vault. Fails unless the staking pool is
+    /// custodial and the mint of its reward token pool is the mint of its stake vault, and
+    /// fails when the staking pool vests claimed rewards.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` authority.
+    ///   1. `[writable]` Stake account.
+    ///   2. `[writable]` Staking pool.
+    ///   3. `[]` Clock sysvar.
+    ///   4. `[writable]` Reward token pool.
+    ///   5. `[writable]` Stake vault.
+    ///   6. `[]` Staking program derived that owns the reward token pool and stake vault.
+    ///   7. `[]` Token program.
+    CompoundReward,
+    /// Make an empty staking pool custodial: create its stake vault for the stake mint, after
+    /// which `Deposit`, `Withdraw`, `EmergencyWithdraw` and `CompoundReward` move tokens in and
+    /// out of the vault.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool admin authority.
+    ///   1. `[signer, writable]` Payer of the stake vault rent.
+    ///   2. `[writable]` Staking pool.
+    ///   3. `[writable]` Stake vault - uninitialized, derived from the staking pool.
+    ///   4. `[]` Stake mint.
+    ///   5. `[]` Staking program derived that owns the stake vault.
+    ///   6. `[]` System program.
+    ///   7. `[]` Token program.
+    ///   8. `[]` Rent sysvar.
+    InitStakeVault,
+    /// Set the penalty of claims before the earliest reward claim time, in basis points of the
+    /// claimed rewards at the start of the reward period, decreasing linearly to none at the
+    /// earliest reward claim time. 0 rejects early claims.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool admin authority.
+    ///   1. `[writable]` Staking pool.
+    SetEarlyClaimPenalty {
+        penalty_bps: u16,
+        mode: EarlyClaimPenaltyMode,
+    },
+    /// Set the vesting schedule of rewards claimed from a staking pool, 0 duration for liquid
+    /// claims. Applies to the rewards already vesting.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool admin authority.
+    ///   1. `[writable]` Staking pool.
+    SetVestingSchedule { cliff: u64, duration: u64 },
+    /// Create the vesting account receiving the claimed rewards of an owner.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer, writable]` Payer of the vesting account rent.
+    ///   1. `[]` Staking pool.
+    ///   2. `[writable]` Vesting account - uninitialized, derived from the staking pool and owner.
+    ///   3. `[]` Vesting account owner.
+    ///   4. `[]` System program.
+    ///   5. `[]` Rent sysvar.
+    CreateVestingAccount,
+    /// Withdraw the vested rewards of a vesting account.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Vesting account owner.
+    ///   1. `[writable]` Staking pool.
+    ///   2. `[writable]` Vesting account.
+    ///   3. `[writable]` Reward token pool.
+    ///   4. `[writable]` Reward destination.
+    ///   5. `[]` Staking Pool owner derived from staking pool pubkey
+    ///   6. `[]` Clock sysvar.
+    ///   7. `[]` Token program.
+    WithdrawVested,
+    /// Create a reward distributor emitting `supply` reward tokens over `duration` to the
+    /// staking pools it feeds, by weight.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Transfer reward token authority.
+    ///   1. `[writable]` Reward token supply.
+    ///   2. `[writable]` Reward distributor token vault - uninitialized.
+    ///   3. `[writable]` Reward distributor - uninitialized.
+    ///   4. `[]` Reward token mint.
+    ///   5. `[]` Reward distributor derived that owns the token vault.
+    ///   6. `[]` Clock sysvar.
+    ///   7. `[]` Rent sysvar.
+    ///   8. `[]` Token program.
+    InitRewardDistributor {
+        supply: u64,
+        duration: u64,
+        bump_seed: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+        admin_authority: Pubkey,
+        time_basis: TimeBasis,
+    },
+    /// Set the weight of a staking pool in a reward distributor, registering the pool if
+    /// needed. 0 stops feeding the pool. Fails when the staking pool has a different time
+    /// basis than the reward distributor.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Reward distributor admin authority.
+    ///   1. `[writable]` Reward distributor.
+    ///   2. `[]` Staking pool.
+    ///   3. `[]` Clock sysvar.
+    SetDistributorWeight { weight: u64 },
+    /// Create a gauge splitting `emission_per_epoch` reward tokens between staking pools by
+    /// the votes of the stakers of a governance staking pool. The governance staking pool must
+    /// keep time in `time_basis`. The emissions are transferred out of the gauge reward token
+    /// vault, which anyone can fund.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[writable]` Gauge - uninitialized.
+    ///   1. `[]` Governance staking pool.
+    ///   2. `[]` Clock sysvar.
+    ///   3. `[]` Rent sysvar.
+    ///   4. `[writable]` Gauge reward token vault - uninitialized.
+    ///   5. `[]` Reward token mint.
+    ///   6. `[]` Gauge derived that owns the reward token vault.
+    ///   7. `[]` Token program.
+    InitGauge {
+        epoch_duration: u64,
+        emission_per_epoch: u64,
+        bump_seed: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+        admin_authority: Pubkey,
+        time_basis: TimeBasis,
+    },
+    /// Add a staking pool to a gauge. The staking pool admin authority must be the gauge
+    /// derived address, the staking pool must keep time in the time basis of the gauge and its
+    /// reward token pool must be in the mint of the gauge reward token vault.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Gauge admin authority.
+    ///   1. `[writable]` Gauge.
+    ///   2. `[]` Staking pool.
+    ///   3. `[]` Reward token pool of the staking pool.
+    ///   4. `[]` Gauge reward token vault.
+    AddGaugePool,
+    /// Create the gauge vote of a stake account in the governance staking pool.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Stake account owner.
+    ///   1. `[signer, writable]` Payer of the gauge vote rent.
+    ///   2. `[]` Gauge.
+    ///   3. `[writable]` Gauge vote - uninitialized, derived from the gauge and stake account.
+    ///   4. `[]` Stake account.
+    ///   5. `[]` System program.
+    ///   6. `[]` Rent sysvar.
+    CreateGaugeVote,
+    /// Set the weight of the staking pool at `pool_index` in the gauge vote of a stake account.
+    /// Takes effect from the next commit of the vote.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Stake account owner.
+    ///   1. `[]` Stake account.
+    ///   2. `[writable]` Gauge vote.
+    SetGaugeVote { pool_index: u8, weight_bps: u16 },
+    /// Count a gauge vote with the voting power of its stake account in the current epoch,
+    /// once per vote and epoch, and fails once the epoch has ended or when the vote has no
+    /// weight. The deposit of the stake account cannot be withdrawn, merged or split until the
+    /// end of the epoch.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Stake account owner.
+    ///   1. `[writable]` Gauge.
+    ///   2. `[writable]` Gauge vote.
+    ///   3. `[writable]` Stake account, in the governance staking pool.
+    ///   4. `[]` Governance staking pool.
+    ///   5. `[]` Clock sysvar.
+    CommitGaugeVote,
+    /// Close an ended gauge epoch, transfer the share of every staking pool of the gauge from
+    /// its votes out of the gauge reward token vault into the reward token pool of the staking
+    /// pool, and set its `rate_per_slot` to emit that share until the end of the new epoch, its
+    /// `end_time`, so the pools stop emitting until the next crank. Fails while the vault holds
+    /// less than the shares. Permissionless.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[writable]` Gauge.
+    ///   1. `[]` Gauge derived, admin authority of the staking pools and owner of the vault.
+    ///   2. `[]` Clock sysvar.
+    ///   3. `[writable]` Gauge reward token vault.
+    ///   4. `[]` Token program.
+    ///   5. `[writable]` Staking pool and `[writable]` its reward token pool, one pair per gauge
+    ///      pool in the order of the gauge.
+    CrankGaugeEpoch,
+    /// Set the longest lock of the stake accounts of a staking pool, full voting power. 0
+    /// disables locks without shortening the existing ones.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Staking pool admin authority.
+    ///   1. `[writable]` Staking pool.
+    SetMaxLockDuration { max_lock_duration: u64 },
+    /// Lock the deposit of a stake account until `lock_end`, no earlier than its current lock
+    /// end and at most the maximum lock duration of the staking pool from now.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[signer]` Stake account owner.
+    ///   1. `[writable]` Stake account.
+    ///   2. `[]` Staking pool.
+    ///   3. `[]` Clock sysvar.
+    ExtendLock { lock_end: u64 },
+    /// Compute the voting power of a stake account at `time`, in the time basis of the staking
+    /// pool, without changing any account. Sets the `VotingPower` return data. The voting power
+    /// is projected from the current deposit and lock, so `time` must not be in the past.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[]` Stake account.
+    ///   1. `[]` Staking pool.
+    ///   2. `[]` Clock sysvar.
+    GetVotingPower { time: u64 },
+    /// Compute the rewards a stake account could claim now without changing any account.
+    /// Sets the `PendingReward` return data. Rewards of a reward distributor feeding the
+    /// staking pool count only once pulled into the pool by `RefreshStakeAccount`.
+    ///
+    /// Accounts expected by this instruction:
+    ///   0. `[]` Stake account.
+    ///   1. `[]` Staking pool.
+    ///   2. `[]` Clock sysvar.
+    QueryPendingReward,
 }
 
 impl StakingInstruction {
@@ -80,6 +495,29 @@ impl StakingInstruction {
                     let (bump_seed_staking_program, rest) = Self::unpack_u8(rest)?;
                     let (pool_owner_authority, rest) = Self::unpack_pubkey(rest)?;
                     let (admin_authority, rest) = Self::unpack_pubkey(rest)?;
+                    // fields added since the first layout are left out by older clients
+                    let (start_time, deposit_before_start, rest) = if rest.is_empty() {
+                        (0, false, rest)
+                    } else {
+                        let (start_time, rest) = Self::unpack_u64(rest)?;
+                        let (deposit_before_start, rest) = Self::unpack_bool(rest)?;
+                        (start_time, deposit_before_start, rest)
+                    };
+                    let (time_basis, rest) = if rest.is_empty() {
+                        (TimeBasis::Slot, rest)
+                    } else {
+                        let (time_basis, rest) = Self::unpack_u8(rest)?;
+                        let time_basis = TimeBasis::try_from(time_basis)
+                            .map_err(|_| StakingError::InstructionUnpackError)?;
+                        (time_basis, rest)
+                    };
+                    let (max_pool_size, max_deposit_per_account, rest) = if rest.is_empty() {
+                        (0, 0, rest)
+                    } else {
+                        let (max_pool_size, rest) = Self::unpack_u64(rest)?;
+                        let (max_deposit_per_account, rest) = Self::unpack_u64(rest)?;
+                        (max_pool_size, max_deposit_per_account, rest)
+                    };
                     Ok((
                         InitStakingPool {
                             supply,
@@ -88,6 +526,11 @@ impl StakingInstruction {
                             bump_seed_staking_program,
                             pool_owner_authority,
                             admin_authority,
+                            start_time,
+                            deposit_before_start,
+                            time_basis,
+                            max_pool_size,
+                            max_deposit_per_account,
                         },
                         rest,
                     ))
@@ -102,6 +545,137 @@ impl StakingInstruction {
                     Ok((Withdraw(amount), rest))
                 }
                 4 => Ok((ClaimReward, rest)),
+                5 => Ok((RefreshStakeAccount, rest)),
+                6 => Ok((EmergencyWithdraw, rest)),
+                7 => Ok((WithdrawUnallocatedRewards, rest)),
+                8 => Ok((CloseStakingPool, rest)),
+                9 => {
+                    let (supply, rest) = Self::unpack_u64(rest)?;
+                    let (duration, rest) = Self::unpack_u64(rest)?;
+                    Ok((StartNewPeriod { supply, duration }, rest))
+                }
+                10 => {
+                    let (max_pool_size, rest) = Self::unpack_u64(rest)?;
+                    let (max_deposit_per_account, rest) = Self::unpack_u64(rest)?;
+                    Ok((
+                        SetDepositLimits {
+                            max_pool_size,
+                            max_deposit_per_account,
+                        },
+                        rest,
+                    ))
+                }
+                11 => {
+                    let (allowlist_enabled, rest) = Self::unpack_bool(rest)?;
+                    let (gatekeeper, rest) = Self::unpack_pubkey(rest)?;
+                    Ok((
+                        SetAllowlistConfig {
+                            allowlist_enabled,
+                            gatekeeper,
+                        },
+                        rest,
+                    ))
+                }
+                12 => Ok((AddToAllowlist, rest)),
+                13 => Ok((RemoveFromAllowlist, rest)),
+                14 => Ok((MergeStakeAccounts, rest)),
+                15 => {
+                    let (amount, rest) = Self::unpack_u64(rest)?;
+                    let (reward_amount, rest) = Self::unpack_option_u64(rest)?;
+                    Ok((
+                        SplitStakeAccount {
+                            amount,
+                            reward_amount,
+                        },
+                        rest,
+                    ))
+                }
+                16 => Ok((CompoundReward, rest)),
+                17 => Ok((InitStakeVault, rest)),
+                18 => {
+                    let (penalty_bps, rest) = Self::unpack_u16(rest)?;
+                    let (mode, rest) = Self::unpack_u8(rest)?;
+                    let mode = EarlyClaimPenaltyMode::try_from(mode)
+                        .map_err(|_| StakingError::InstructionUnpackError)?;
+                    Ok((SetEarlyClaimPenalty { penalty_bps, mode }, rest))
+                }
+                19 => {
+                    let (cliff, rest) = Self::unpack_u64(rest)?;
+                    let (duration, rest) = Self::unpack_u64(rest)?;
+                    Ok((SetVestingSchedule { cliff, duration }, rest))
+                }
+                20 => Ok((CreateVestingAccount, rest)),
+                21 => Ok((WithdrawVested, rest)),
+                22 => {
+                    let (supply, rest) = Self::unpack_u64(rest)?;
+                    let (duration, rest) = Self::unpack_u64(rest)?;
+                    let (bump_seed, rest) = Self::unpack_u8(rest)?;
+                    let (admin_authority, rest) = Self::unpack_pubkey(rest)?;
+                    let (time_basis, rest) = Self::unpack_u8(rest)?;
+                    let time_basis = TimeBasis::try_from(time_basis)
+                        .map_err(|_| StakingError::InstructionUnpackError)?;
+                    Ok((
+                        InitRewardDistributor {
+                            supply,
+                            duration,
+                            bump_seed,
+                            admin_authority,
+                            time_basis,
+                        },
+                        rest,
+                    ))
+                }
+                23 => {
+                    let (weight, rest) = Self::unpack_u64(rest)?;
+                    Ok((SetDistributorWeight { weight }, rest))
+                }
+                24 => {
+                    let (epoch_duration, rest) = Self::unpack_u64(rest)?;
+                    let (emission_per_epoch, rest) = Self::unpack_u64(rest)?;
+                    let (bump_seed, rest) = Self::unpack_u8(rest)?;
+                    let (admin_authority, rest) = Self::unpack_pubkey(rest)?;
+                    let (time_basis, rest) = Self::unpack_u8(rest)?;
+                    let time_basis = TimeBasis::try_from(time_basis)
+                        .map_err(|_| StakingError::InstructionUnpackError)?;
+                    Ok((
+                        InitGauge {
+                            epoch_duration,
+                            emission_per_epoch,
+                            bump_seed,
+                            admin_authority,
+                            time_basis,
+                        },
+                        rest,
+                    ))
+                }
+                25 => Ok((AddGaugePool, rest)),
+                26 => Ok((CreateGaugeVote, rest)),
+                27 => {
+                    let (pool_index, rest) = Self::unpack_u8(rest)?;
+                    let (weight_bps, rest) = Self::unpack_u16(rest)?;
+                    Ok((
+                        SetGaugeVote {
+                            pool_index,
+                            weight_bps,
+                        },
+                        rest,
+                    ))
+                }
+                28 => Ok((CommitGaugeVote, rest)),
+                29 => Ok((CrankGaugeEpoch, rest)),
+                30 => {
+                    let (max_lock_duration, rest) = Self::unpack_u64(rest)?;
+                    Ok((SetMaxLockDuration { max_lock_duration }, rest))
+                }
+                31 => {
+                    let (lock_end, rest) = Self::unpack_u64(rest)?;
+                    Ok((ExtendLock { lock_end }, rest))
+                }
+                32 => {
+                    let (time, rest) = Self::unpack_u64(rest)?;
+                    Ok((GetVotingPower { time }, rest))
+                }
+                33 => Ok((QueryPendingReward, rest)),
                 _ => {
                     msg!("Instruction cannot be unpacked");
                     Err(StakingError::InstructionUnpackError.into())
@@ -150,6 +724,42 @@ impl StakingInstruction {
             .ok_or(StakingError::InstructionUnpackError)?;
         Ok((value, rest))
     }
+    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        if input.len() < 2 {
+            msg!("u16 cannot be unpacked");
+            return Err(StakingError::InstructionUnpackError.into());
+        }
+        let (bytes, rest) = input.split_at(2);
+        let value = bytes
+            .get(..2)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or(StakingError::InstructionUnpackError)?;
+        Ok((value, rest))
+    }
+    fn unpack_option_u64(input: &[u8]) -> Result<(Option<u64>, &[u8]), ProgramError> {
+        let (is_some, rest) = Self::unpack_bool(input)?;
+        let (value, rest) = Self::unpack_u64(rest)?;
+        match (is_some, value) {
+            (true, value) => Ok((Some(value), rest)),
+            (false, 0) => Ok((None, rest)),
+            (false, _) => {
+                msg!("None option must have a zero value");
+                Err(StakingError::InstructionUnpackError.into())
+            }
+        }
+    }
+    fn unpack_bool(input: &[u8]) -> Result<(bool, &[u8]), ProgramError> {
+        let (value, rest) = Self::unpack_u8(input)?;
+        match value {
+            0 => Ok((false, rest)),
+            1 => Ok((true, rest)),
+            _ => {
+                msg!("bool cannot be unpacked");
+                Err(StakingError::InstructionUnpackError.into())
+            }
+        }
+    }
 
     pub fn pack(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(size_of::<Self>());
@@ -161,6 +771,11 @@ impl StakingInstruction {
                 bump_seed_staking_program,
                 pool_owner_authority,
                 admin_authority,
+                start_time,
+                deposit_before_start,
+                time_basis,
+                max_pool_size,
+                max_deposit_per_account,
             } => {
                 buf.push(0);
                 buf.extend_from_slice(&supply.to_le_bytes());
@@ -169,6 +784,21 @@ impl StakingInstruction {
                 buf.extend_from_slice(&bump_seed_staking_program.to_le_bytes());
                 buf.extend_from_slice(pool_owner_authority.as_ref());
                 buf.extend_from_slice(admin_authority.as_ref());
+                // trailing groups of fields left at their defaults are left out, as in the
+                // layouts of older clients
+                let with_limits = max_pool_size != 0 || max_deposit_per_account != 0;
+                let with_time_basis = with_limits || time_basis != TimeBasis::Slot;
+                if with_time_basis || start_time != 0 || deposit_before_start {
+                    buf.extend_from_slice(&start_time.to_le_bytes());
+                    buf.push(deposit_before_start as u8);
+                }
+                if with_time_basis {
+                    buf.push(time_basis as u8);
+                }
+                if with_limits {
+                    buf.extend_from_slice(&max_pool_size.to_le_bytes());
+                    buf.extend_from_slice(&max_deposit_per_account.to_le_bytes());
+                }
             }
             Self::CreateStakeAccount => {
                 buf.push(1);
@@ -184,6 +814,146 @@ impl StakingInstruction {
             Self::ClaimReward => {
                 buf.push(4);
             }
+            Self::RefreshStakeAccount => {
+                buf.push(5);
+            }
+            Self::EmergencyWithdraw => {
+                buf.push(6);
+            }
+            Self::WithdrawUnallocatedRewards => {
+                buf.push(7);
+            }
+            Self::CloseStakingPool => {
+                buf.push(8);
+            }
+            Self::StartNewPeriod { supply, duration } => {
+                buf.push(9);
+                buf.extend_from_slice(&supply.to_le_bytes());
+                buf.extend_from_slice(&duration.to_le_bytes());
+            }
+            Self::SetDepositLimits {
+                max_pool_size,
+                max_deposit_per_account,
+            } => {
+                buf.push(10);
+                buf.extend_from_slice(&max_pool_size.to_le_bytes());
+                buf.extend_from_slice(&max_deposit_per_account.to_le_bytes());
+            }
+            Self::SetAllowlistConfig {
+                allowlist_enabled,
+                gatekeeper,
+            } => {
+                buf.push(11);
+                buf.push(allowlist_enabled as u8);
+                buf.extend_from_slice(gatekeeper.as_ref());
+            }
+            Self::AddToAllowlist => {
+                buf.push(12);
+            }
+            Self::RemoveFromAllowlist => {
+                buf.push(13);
+            }
+            Self::MergeStakeAccounts => {
+                buf.push(14);
+            }
+            Self::SplitStakeAccount {
+                amount,
+                reward_amount,
+            } => {
+                buf.push(15);
+                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.push(reward_amount.is_some() as u8);
+                buf.extend_from_slice(&reward_amount.unwrap_or(0).to_le_bytes());
+            }
+            Self::CompoundReward => {
+                buf.push(16);
+            }
+            Self::InitStakeVault => {
+                buf.push(17);
+            }
+            Self::SetEarlyClaimPenalty { penalty_bps, mode } => {
+                buf.push(18);
+                buf.extend_from_slice(&penalty_bps.to_le_bytes());
+                buf.push(mode as u8);
+            }
+            Self::SetVestingSchedule { cliff, duration } => {
+                buf.push(19);
+                buf.extend_from_slice(&cliff.to_le_bytes());
+                buf.extend_from_slice(&duration.to_le_bytes());
+            }
+            Self::CreateVestingAccount => {
+                buf.push(20);
+            }
+            Self::WithdrawVested => {
+                buf.push(21);
+            }
+            Self::InitRewardDistributor {
+                supply,
+                duration,
+                bump_seed,
+                admin_authority,
+                time_basis,
+            } => {
+                buf.push(22);
+                buf.extend_from_slice(&supply.to_le_bytes());
+                buf.extend_from_slice(&duration.to_le_bytes());
+                buf.extend_from_slice(&bump_seed.to_le_bytes());
+                buf.extend_from_slice(admin_authority.as_ref());
+                buf.push(time_basis as u8);
+            }
+            Self::SetDistributorWeight { weight } => {
+                buf.push(23);
+                buf.extend_from_slice(&weight.to_le_bytes());
+            }
+            Self::InitGauge {
+                epoch_duration,
+                emission_per_epoch,
+                bump_seed,
+                admin_authority,
+                time_basis,
+            } => {
+                buf.push(24);
+                buf.extend_from_slice(&epoch_duration.to_le_bytes());
+                buf.extend_from_slice(&emission_per_epoch.to_le_bytes());
+                buf.extend_from_slice(&bump_seed.to_le_bytes());
+                buf.extend_from_slice(admin_authority.as_ref());
+                buf.push(time_basis as u8);
+            }
+            Self::AddGaugePool => {
+                buf.push(25);
+            }
+            Self::CreateGaugeVote => {
+                buf.push(26);
+            }
+            Self::SetGaugeVote {
+                pool_index,
+                weight_bps,
+            } => {
+                buf.push(27);
+                buf.extend_from_slice(&pool_index.to_le_bytes());
+                buf.extend_from_slice(&weight_bps.to_le_bytes());
+            }
+            Self::CommitGaugeVote => {
+                buf.push(28);
+            }
+            Self::CrankGaugeEpoch => {
+                buf.push(29);
+            }
+            Self::SetMaxLockDuration { max_lock_duration } => {
+                buf.push(30);
+                buf.extend_from_slice(&max_lock_duration.to_le_bytes());
+            }
+            Self::ExtendLock { lock_end } => {
+                buf.push(31);
+                buf.extend_from_slice(&lock_end.to_le_bytes());
+            }
+            Self::GetVotingPower { time } => {
+                buf.push(32);
+                buf.extend_from_slice(&time.to_le_bytes());
+            }
+            Self::QueryPendingReward => {
+                buf.push(33);
+            }
         };
         buf
     }
@@ -221,6 +991,7 @@ pub fn create_stake_account(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn claim_reward(
     program_id: Pubkey,
     stake_account_owner: Pubkey,
@@ -280,6 +1051,46 @@ pub fn init_staking_pool(
     reward_token_mint: Pubkey,
     staking_pool_owner_derived: Pubkey,
     admin_authority: Pubkey,
+) -> Instruction {
+    init_staking_pool_with_options(
+        program_id,
+        supply,
+        duration,
+        earliest_reward_claim_time,
+        0,
+        false,
+        TimeBasis::Slot,
+        0,
+        0,
+        transfer_reward_token_authority,
+        reward_token_supply,
+        reward_token_pool,
+        staking_pool,
+        reward_token_mint,
+        staking_pool_owner_derived,
+        admin_authority,
+    )
+}
+
+/// Creates an InitStakingPool instruction with a start time, time basis and deposit limits
+#[allow(clippy::too_many_arguments)]
+pub fn init_staking_pool_with_options(
+    program_id: Pubkey,
+    supply: u64,
+    duration: u64,
+    earliest_reward_claim_time: Slot,
+    start_time: Slot,
+    deposit_before_start: bool,
+    time_basis: TimeBasis,
+    max_pool_size: u64,
+    max_deposit_per_account: u64,
+    transfer_reward_token_authority: Pubkey,
+    reward_token_supply: Pubkey,
+    reward_token_pool: Pubkey,
+    staking_pool: Pubkey,
+    reward_token_mint: Pubkey,
+    staking_pool_owner_derived: Pubkey,
+    admin_authority: Pubkey,
 ) -> Instruction {
     let (staking_program_derived, bump_seed) =
         Pubkey::find_program_address(&[staking_pool.as_ref()], &program_id);
@@ -313,6 +1124,11 @@ pub fn init_staking_pool(
             bump_seed_staking_program: bump_seed,
             pool_owner_authority: staking_pool_owner_derived,
             admin_authority,
+            start_time,
+            deposit_before_start,
+            time_basis,
+            max_pool_size,
+            max_deposit_per_account,
         }
         .pack(),
     }
@@ -360,3 +1176,1093 @@ pub fn withdraw(
         data: Withdraw(amount).pack(),
     }
 }
+
+pub fn refresh_stake_account(
+    program_id: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    let write_accounts = create_write_accounts(vec![stake_account, staking_pool]);
+
+    let accounts = write_accounts
+        .chain(vec![AccountMeta::new_readonly(sysvar::clock::id(), false)])
+        .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: RefreshStakeAccount.pack(),
+    }
+}
+
+pub fn emergency_withdraw(
+    program_id: Pubkey,
+    authority: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    let write_accounts = create_write_accounts(vec![stake_account, staking_pool]);
+
+    let accounts = vec![AccountMeta::new_readonly(authority, true)]
+        .into_iter()
+        .chain(write_accounts)
+        .chain(vec![AccountMeta::new_readonly(sysvar::clock::id(), false)])
+        .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: EmergencyWithdraw.pack(),
+    }
+}
+
+pub fn withdraw_unallocated_rewards(
+    program_id: Pubkey,
+    admin_authority: Pubkey,
+    staking_pool: Pubkey,
+    reward_token_pool: Pubkey,
+    destination: Pubkey,
+) -> Instruction {
+    let (staking_program_derived, _bump_seed) =
+        Pubkey::find_program_address(&[staking_pool.as_ref()], &program_id);
+
+    let write_accounts = create_write_accounts(vec![staking_pool, reward_token_pool, destination]);
+
+    let read_accounts = create_read_accounts(vec![
+        staking_program_derived,
+        sysvar::clock::id(),
+        spl_token::id(),
+    ]);
+
+    let accounts = vec![AccountMeta::new_readonly(admin_authority, true)]
+        .into_iter()
+        .chain(write_accounts)
+        .chain(read_accounts)
+        .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: WithdrawUnallocatedRewards.pack(),
+    }
+}
+
+pub fn close_staking_pool(
+    program_id: Pubkey,
+    owner_authority: Pubkey,
+    staking_pool: Pubkey,
+    reward_token_pool: Pubkey,
+    reward_destination: Pubkey,
+    destination: Pubkey,
+) -> Instruction {
+    let (staking_program_derived, _bump_seed) =
+        Pubkey::find_program_address(&[staking_pool.as_ref()], &program_id);
+
+    let write_accounts = create_write_accounts(vec![
+        staking_pool,
+        reward_token_pool,
+        reward_destination,
+        destination,
+    ]);
+
+    let read_accounts = create_read_accounts(vec![
+        staking_program_derived,
+        sysvar::clock::id(),
+        spl_token::id(),
+    ]);
+
+    let accounts = vec![AccountMeta::new_readonly(owner_authority, true)]
+        .into_iter()
+        .chain(write_accounts)
+        .chain(read_accounts)
+        .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: CloseStakingPool.pack(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_new_period(
+    program_id: Pubkey,
+    supply: u64,
+    duration: u64,
+    admin_authority: Pubkey,
+    transfer_reward_token_authority: Pubkey,
+    reward_token_supply: Pubkey,
+    reward_token_pool: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    let write_accounts =
+        create_write_accounts(vec![reward_token_supply, reward_token_pool, staking_pool]);
+
+    let read_accounts = create_read_accounts(vec![sysvar::clock::id(), spl_token::id()]);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(admin_authority, true),
+        AccountMeta::new_readonly(transfer_reward_token_authority, true),
+    ]
+    .into_iter()
+    .chain(write_accounts)
+    .chain(read_accounts)
+    .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: StartNewPeriod { supply, duration }.pack(),
+    }
+}
+
+/// Creates `SetDepositLimits` instruction
+pub fn set_deposit_limits(
+    program_id: Pubkey,
+    max_pool_size: u64,
+    max_deposit_per_account: u64,
+    admin_authority: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(admin_authority, true),
+            AccountMeta::new(staking_pool, false),
+        ],
+        data: SetDepositLimits {
+            max_pool_size,
+            max_deposit_per_account,
+        }
+        .pack(),
+    }
+}
+
+/// Creates `CreateStakeAccount` instruction for an allowlisted staking pool, signed by its
+/// gatekeeper
+pub fn create_stake_account_with_gatekeeper(
+    program_id: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+    stake_account_owner: Pubkey,
+    gatekeeper: Pubkey,
+) -> Instruction {
+    let mut instruction =
+        create_stake_account(program_id, stake_account, staking_pool, stake_account_owner);
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(gatekeeper, true));
+    instruction
+}
+
+/// Creates `CreateStakeAccount` instruction for an allowlisted staking pool, proven by the
+/// allowlist entry of the owner
+pub fn create_stake_account_with_allowlist_entry(
+    program_id: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+    stake_account_owner: Pubkey,
+) -> Instruction {
+    let (allowlist_entry, _) =
+        find_allowlist_entry_address(&program_id, &staking_pool, &stake_account_owner);
+    let mut instruction =
+        create_stake_account(program_id, stake_account, staking_pool, stake_account_owner);
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(allowlist_entry, false));
+    instruction
+}
+
+/// Creates `SetAllowlistConfig` instruction
+pub fn set_allowlist_config(
+    program_id: Pubkey,
+    allowlist_enabled: bool,
+    gatekeeper: Pubkey,
+    admin_authority: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(admin_authority, true),
+            AccountMeta::new(staking_pool, false),
+        ],
+        data: SetAllowlistConfig {
+            allowlist_enabled,
+            gatekeeper,
+        }
+        .pack(),
+    }
+}
+
+/// Creates `AddToAllowlist` instruction
+pub fn add_to_allowlist(
+    program_id: Pubkey,
+    admin_authority: Pubkey,
+    payer: Pubkey,
+    staking_pool: Pubkey,
+    owner: Pubkey,
+) -> Instruction {
+    let (allowlist_entry, _) = find_allowlist_entry_address(&program_id, &staking_pool, &owner);
+    let read_accounts = create_read_accounts(vec![
+        owner,
+        solana_program::system_program::id(),
+        sysvar::rent::id(),
+    ]);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(admin_authority, true),
+        AccountMeta::new(payer, true),
+        AccountMeta::new_readonly(staking_pool, false),
+        AccountMeta::new(allowlist_entry, false),
+    ]
+    .into_iter()
+    .chain(read_accounts)
+    .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: AddToAllowlist.pack(),
+    }
+}
+
+/// Creates `RemoveFromAllowlist` instruction
+pub fn remove_from_allowlist(
+    program_id: Pubkey,
+    admin_authority: Pubkey,
+    staking_pool: Pubkey,
+    owner: Pubkey,
+    destination: Pubkey,
+) -> Instruction {
+    let (allowlist_entry, _) = find_allowlist_entry_address(&program_id, &staking_pool, &owner);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(admin_authority, true),
+            AccountMeta::new_readonly(staking_pool, false),
+            AccountMeta::new(allowlist_entry, false),
+            AccountMeta::new(destination, false),
+        ],
+        data: RemoveFromAllowlist.pack(),
+    }
+}
+
+/// Creates `MergeStakeAccounts` instruction
+pub fn merge_stake_accounts(
+    program_id: Pubkey,
+    stake_account_owner: Pubkey,
+    destination_stake_account: Pubkey,
+    source_stake_account: Pubkey,
+    staking_pool: Pubkey,
+    rent_destination: Pubkey,
+) -> Instruction {
+    let write_accounts = create_write_accounts(vec![
+        destination_stake_account,
+        source_stake_account,
+        staking_pool,
+        rent_destination,
+    ]);
+
+    let accounts = vec![AccountMeta::new_readonly(stake_account_owner, true)]
+        .into_iter()
+        .chain(write_accounts)
+        .chain(vec![AccountMeta::new_readonly(sysvar::clock::id(), false)])
+        .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: MergeStakeAccounts.pack(),
+    }
+}
+
+/// Creates `SplitStakeAccount` instruction. In an allowlisted staking pool, append the
+/// gatekeeper or the allowlist entry of `new_stake_account_owner` as in
+/// `create_stake_account_with_gatekeeper` and `create_stake_account_with_allowlist_entry`.
+#[allow(clippy::too_many_arguments)]
+pub fn split_stake_account(
+    program_id: Pubkey,
+    amount: u64,
+    reward_amount: Option<u64>,
+    stake_account_owner: Pubkey,
+    stake_account: Pubkey,
+    new_stake_account: Pubkey,
+    staking_pool: Pubkey,
+    new_stake_account_owner: Pubkey,
+) -> Instruction {
+    let write_accounts =
+        create_write_accounts(vec![stake_account, new_stake_account, staking_pool]);
+
+    let read_accounts = create_read_accounts(vec![
+        new_stake_account_owner,
+        sysvar::clock::id(),
+        sysvar::rent::id(),
+    ]);
+
+    let accounts = vec![AccountMeta::new_readonly(stake_account_owner, true)]
+        .into_iter()
+        .chain(write_accounts)
+        .chain(read_accounts)
+        .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: SplitStakeAccount {
+            amount,
+            reward_amount,
+        }
+        .pack(),
+    }
+}
+
+/// Creates `CompoundReward` instruction
+pub fn compound_reward(
+    program_id: Pubkey,
+    authority: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+    reward_token_pool: Pubkey,
+) -> Instruction {
+    let (stake_vault, _) = find_stake_vault_address(&program_id, &staking_pool);
+    let (derived_staking_program_address, _) =
+        Pubkey::find_program_address(&[staking_pool.as_ref()], &program_id);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(authority, true),
+        AccountMeta::new(stake_account, false),
+        AccountMeta::new(staking_pool, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new(reward_token_pool, false),
+        AccountMeta::new(stake_vault, false),
+        AccountMeta::new_readonly(derived_staking_program_address, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+
+    Instruction {
+        program_id,
+        accounts,
+        data: CompoundReward.pack(),
+    }
+}
+
+/// Creates `InitStakeVault` instruction
+pub fn init_stake_vault(
+    program_id: Pubkey,
+    admin_authority: Pubkey,
+    payer: Pubkey,
+    staking_pool: Pubkey,
+    stake_mint: Pubkey,
+) -> Instruction {
+    let (stake_vault, _) = find_stake_vault_address(&program_id, &staking_pool);
+    let (derived_staking_program_address, _) =
+        Pubkey::find_program_address(&[staking_pool.as_ref()], &program_id);
+
+    let read_accounts = create_read_accounts(vec![
+        stake_mint,
+        derived_staking_program_address,
+        solana_program::system_program::id(),
+        spl_token::id(),
+        sysvar::rent::id(),
+    ]);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(admin_authority, true),
+        AccountMeta::new(payer, true),
+        AccountMeta::new(staking_pool, false),
+        AccountMeta::new(stake_vault, false),
+    ]
+    .into_iter()
+    .chain(read_accounts)
+    .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: InitStakeVault.pack(),
+    }
+}
+
+/// Creates `Deposit` instruction for a custodial staking pool, transferring `amount` from
+/// `source` owned by `authority` into the stake vault
+pub fn deposit_custodial(
+    program_id: Pubkey,
+    amount: u64,
+    authority: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+    source: Pubkey,
+) -> Instruction {
+    let (stake_vault, _) = find_stake_vault_address(&program_id, &staking_pool);
+    let mut instruction = deposit(program_id, amount, authority, stake_account, staking_pool);
+    instruction.accounts.extend(vec![
+        AccountMeta::new(source, false),
+        AccountMeta::new(stake_vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]);
+    instruction
+}
+
+fn custodial_withdraw_accounts(
+    program_id: &Pubkey,
+    staking_pool: &Pubkey,
+    destination: Pubkey,
+) -> Vec<AccountMeta> {
+    let (stake_vault, _) = find_stake_vault_address(program_id, staking_pool);
+    let (derived_staking_program_address, _) =
+        Pubkey::find_program_address(&[staking_pool.as_ref()], program_id);
+    vec![
+        AccountMeta::new(stake_vault, false),
+        AccountMeta::new(destination, false),
+        AccountMeta::new_readonly(derived_staking_program_address, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]
+}
+
+/// Creates `Withdraw` instruction for a custodial staking pool, transferring `amount` from
+/// the stake vault to `destination`
+pub fn withdraw_custodial(
+    program_id: Pubkey,
+    amount: u64,
+    authority: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+    destination: Pubkey,
+) -> Instruction {
+    let mut instruction = withdraw(program_id, amount, authority, stake_account, staking_pool);
+    instruction.accounts.extend(custodial_withdraw_accounts(
+        &program_id,
+        &staking_pool,
+        destination,
+    ));
+    instruction
+}
+
+/// Creates `EmergencyWithdraw` instruction for a custodial staking pool, transferring the
+/// whole deposit from the stake vault to `destination`
+pub fn emergency_withdraw_custodial(
+    program_id: Pubkey,
+    authority: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+    destination: Pubkey,
+) -> Instruction {
+    let mut instruction = emergency_withdraw(program_id, authority, stake_account, staking_pool);
+    instruction.accounts.extend(custodial_withdraw_accounts(
+        &program_id,
+        &staking_pool,
+        destination,
+    ));
+    instruction
+}
+
+/// Creates `SetEarlyClaimPenalty` instruction
+pub fn set_early_claim_penalty(
+    program_id: Pubkey,
+    penalty_bps: u16,
+    mode: EarlyClaimPenaltyMode,
+    admin_authority: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(admin_authority, true),
+            AccountMeta::new(staking_pool, false),
+        ],
+        data: SetEarlyClaimPenalty { penalty_bps, mode }.pack(),
+    }
+}
+
+/// Creates `SetVestingSchedule` instruction
+pub fn set_vesting_schedule(
+    program_id: Pubkey,
+    cliff: u64,
+    duration: u64,
+    admin_authority: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(admin_authority, true),
+            AccountMeta::new(staking_pool, false),
+        ],
+        data: SetVestingSchedule { cliff, duration }.pack(),
+    }
+}
+
+/// Creates `CreateVestingAccount` instruction
+pub fn create_vesting_account(
+    program_id: Pubkey,
+    payer: Pubkey,
+    staking_pool: Pubkey,
+    owner: Pubkey,
+) -> Instruction {
+    let (vesting_account, _) = find_vesting_account_address(&program_id, &staking_pool, &owner);
+    let read_accounts = create_read_accounts(vec![
+        owner,
+        solana_program::system_program::id(),
+        sysvar::rent::id(),
+    ]);
+
+    let accounts = vec![
+        AccountMeta::new(payer, true),
+        AccountMeta::new_readonly(staking_pool, false),
+        AccountMeta::new(vesting_account, false),
+    ]
+    .into_iter()
+    .chain(read_accounts)
+    .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: CreateVestingAccount.pack(),
+    }
+}
+
+/// Creates `ClaimReward` instruction for a staking pool vesting rewards, topping up the
+/// vesting account of the owner
+pub fn claim_reward_vested(
+    program_id: Pubkey,
+    stake_account_owner: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+    reward_token_pool: Pubkey,
+) -> Instruction {
+    let (vesting_account, _) =
+        find_vesting_account_address(&program_id, &staking_pool, &stake_account_owner);
+    claim_reward(
+        program_id,
+        stake_account_owner,
+        stake_account,
+        staking_pool,
+        reward_token_pool,
+        vesting_account,
+        None,
+        None,
+    )
+}
+
+/// Creates `WithdrawVested` instruction
+pub fn withdraw_vested(
+    program_id: Pubkey,
+    owner: Pubkey,
+    staking_pool: Pubkey,
+    reward_token_pool: Pubkey,
+    reward_destination: Pubkey,
+) -> Instruction {
+    let (vesting_account, _) = find_vesting_account_address(&program_id, &staking_pool, &owner);
+    let (staking_program_derived, _bump_seed) =
+        Pubkey::find_program_address(&[staking_pool.as_ref()], &program_id);
+
+    let write_accounts = create_write_accounts(vec![
+        staking_pool,
+        vesting_account,
+        reward_token_pool,
+        reward_destination,
+    ]);
+
+    let read_accounts = create_read_accounts(vec![
+        staking_program_derived,
+        sysvar::clock::id(),
+        spl_token::id(),
+    ]);
+
+    let accounts = vec![AccountMeta::new_readonly(owner, true)]
+        .into_iter()
+        .chain(write_accounts)
+        .chain(read_accounts)
+        .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: WithdrawVested.pack(),
+    }
+}
+
+/// Creates `RefreshStakeAccount` instruction for a staking pool fed by `reward_distributor`,
+/// the only instruction pulling its share into the pool
+pub fn refresh_stake_account_with_distributor(
+    program_id: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+    reward_distributor: Pubkey,
+    reward_distributor_vault: Pubkey,
+    reward_token_pool: Pubkey,
+) -> Instruction {
+    let (reward_distributor_derived, _bump_seed) =
+        Pubkey::find_program_address(&[reward_distributor.as_ref()], &program_id);
+    let mut instruction = refresh_stake_account(program_id, stake_account, staking_pool);
+    instruction.accounts.extend(vec![
+        AccountMeta::new(reward_distributor, false),
+        AccountMeta::new(reward_distributor_vault, false),
+        AccountMeta::new_readonly(reward_distributor_derived, false),
+        AccountMeta::new(reward_token_pool, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]);
+    instruction
+}
+
+/// Creates `InitRewardDistributor` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn init_reward_distributor(
+    program_id: Pubkey,
+    supply: u64,
+    duration: u64,
+    time_basis: TimeBasis,
+    transfer_reward_token_authority: Pubkey,
+    reward_token_supply: Pubkey,
+    reward_distributor_vault: Pubkey,
+    reward_distributor: Pubkey,
+    reward_token_mint: Pubkey,
+    admin_authority: Pubkey,
+) -> Instruction {
+    let (reward_distributor_derived, bump_seed) =
+        Pubkey::find_program_address(&[reward_distributor.as_ref()], &program_id);
+
+    let write_accounts = create_write_accounts(vec![
+        reward_token_supply,
+        reward_distributor_vault,
+        reward_distributor,
+    ]);
+
+    let read_accounts = create_read_accounts(vec![
+        reward_token_mint,
+        reward_distributor_derived,
+        sysvar::clock::id(),
+        sysvar::rent::id(),
+        spl_token::id(),
+    ]);
+
+    let accounts = vec![AccountMeta::new_readonly(
+        transfer_reward_token_authority,
+        true,
+    )]
+    .into_iter()
+    .chain(write_accounts)
+    .chain(read_accounts)
+    .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: InitRewardDistributor {
+            supply,
+            duration,
+            bump_seed,
+            admin_authority,
+            time_basis,
+        }
+        .pack(),
+    }
+}
+
+/// Creates `SetDistributorWeight` instruction
+pub fn set_distributor_weight(
+    program_id: Pubkey,
+    weight: u64,
+    admin_authority: Pubkey,
+    reward_distributor: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(admin_authority, true),
+            AccountMeta::new(reward_distributor, false),
+            AccountMeta::new_readonly(staking_pool, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: SetDistributorWeight { weight }.pack(),
+    }
+}
+
+/// Creates `InitGauge` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn init_gauge(
+    program_id: Pubkey,
+    epoch_duration: u64,
+    emission_per_epoch: u64,
+    time_basis: TimeBasis,
+    gauge: Pubkey,
+    governance_pool: Pubkey,
+    reward_token_vault: Pubkey,
+    reward_token_mint: Pubkey,
+    admin_authority: Pubkey,
+) -> Instruction {
+    let (gauge_derived, bump_seed) = Pubkey::find_program_address(&[gauge.as_ref()], &program_id);
+
+    let read_accounts = create_read_accounts(vec![
+        governance_pool,
+        sysvar::clock::id(),
+        sysvar::rent::id(),
+    ]);
+
+    let vault_accounts = vec![
+        AccountMeta::new(reward_token_vault, false),
+        AccountMeta::new_readonly(reward_token_mint, false),
+        AccountMeta::new_readonly(gauge_derived, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+
+    let accounts = vec![AccountMeta::new(gauge, false)]
+        .into_iter()
+        .chain(read_accounts)
+        .chain(vault_accounts)
+        .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: InitGauge {
+            epoch_duration,
+            emission_per_epoch,
+            bump_seed,
+            admin_authority,
+            time_basis,
+        }
+        .pack(),
+    }
+}
+
+/// Creates `AddGaugePool` instruction
+pub fn add_gauge_pool(
+    program_id: Pubkey,
+    admin_authority: Pubkey,
+    gauge: Pubkey,
+    staking_pool: Pubkey,
+    reward_token_pool: Pubkey,
+    reward_token_vault: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(admin_authority, true),
+            AccountMeta::new(gauge, false),
+            AccountMeta::new_readonly(staking_pool, false),
+            AccountMeta::new_readonly(reward_token_pool, false),
+            AccountMeta::new_readonly(reward_token_vault, false),
+        ],
+        data: AddGaugePool.pack(),
+    }
+}
+
+/// Creates `CreateGaugeVote` instruction
+pub fn create_gauge_vote(
+    program_id: Pubkey,
+    stake_account_owner: Pubkey,
+    payer: Pubkey,
+    gauge: Pubkey,
+    stake_account: Pubkey,
+) -> Instruction {
+    let (gauge_vote, _) = find_gauge_vote_address(&program_id, &gauge, &stake_account);
+    let read_accounts = create_read_accounts(vec![
+        stake_account,
+        solana_program::system_program::id(),
+        sysvar::rent::id(),
+    ]);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(stake_account_owner, true),
+        AccountMeta::new(payer, true),
+        AccountMeta::new_readonly(gauge, false),
+        AccountMeta::new(gauge_vote, false),
+    ]
+    .into_iter()
+    .chain(read_accounts)
+    .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: CreateGaugeVote.pack(),
+    }
+}
+
+/// Creates `SetGaugeVote` instruction
+pub fn set_gauge_vote(
+    program_id: Pubkey,
+    pool_index: u8,
+    weight_bps: u16,
+    stake_account_owner: Pubkey,
+    gauge: Pubkey,
+    stake_account: Pubkey,
+) -> Instruction {
+    let (gauge_vote, _) = find_gauge_vote_address(&program_id, &gauge, &stake_account);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(stake_account_owner, true),
+            AccountMeta::new_readonly(stake_account, false),
+            AccountMeta::new(gauge_vote, false),
+        ],
+        data: SetGaugeVote {
+            pool_index,
+            weight_bps,
+        }
+        .pack(),
+    }
+}
+
+/// Creates `CommitGaugeVote` instruction
+pub fn commit_gauge_vote(
+    program_id: Pubkey,
+    stake_account_owner: Pubkey,
+    gauge: Pubkey,
+    stake_account: Pubkey,
+    governance_pool: Pubkey,
+) -> Instruction {
+    let (gauge_vote, _) = find_gauge_vote_address(&program_id, &gauge, &stake_account);
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(stake_account_owner, true),
+            AccountMeta::new(gauge, false),
+            AccountMeta::new(gauge_vote, false),
+            AccountMeta::new(stake_account, false),
+            AccountMeta::new_readonly(governance_pool, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: CommitGaugeVote.pack(),
+    }
+}
+
+/// Creates `CrankGaugeEpoch` instruction, `staking_pools` with their reward token pools in the
+/// order of the gauge
+pub fn crank_gauge_epoch(
+    program_id: Pubkey,
+    gauge: Pubkey,
+    reward_token_vault: Pubkey,
+    staking_pools: Vec<(Pubkey, Pubkey)>,
+) -> Instruction {
+    let (gauge_derived, _bump_seed) = Pubkey::find_program_address(&[gauge.as_ref()], &program_id);
+
+    let accounts = vec![
+        AccountMeta::new(gauge, false),
+        AccountMeta::new_readonly(gauge_derived, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new(reward_token_vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]
+    .into_iter()
+    .chain(create_write_accounts(
+        staking_pools
+            .into_iter()
+            .flat_map(|(staking_pool, reward_token_pool)| [staking_pool, reward_token_pool])
+            .collect(),
+    ))
+    .collect();
+
+    Instruction {
+        program_id,
+        accounts,
+        data: CrankGaugeEpoch.pack(),
+    }
+}
+
+/// Creates `SetMaxLockDuration` instruction
+pub fn set_max_lock_duration(
+    program_id: Pubkey,
+    max_lock_duration: u64,
+    admin_authority: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(admin_authority, true),
+            AccountMeta::new(staking_pool, false),
+        ],
+        data: SetMaxLockDuration { max_lock_duration }.pack(),
+    }
+}
+
+/// Creates `ExtendLock` instruction
+pub fn extend_lock(
+    program_id: Pubkey,
+    lock_end: u64,
+    stake_account_owner: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(stake_account_owner, true),
+            AccountMeta::new(stake_account, false),
+            AccountMeta::new_readonly(staking_pool, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: ExtendLock { lock_end }.pack(),
+    }
+}
+
+/// Creates `GetVotingPower` instruction
+pub fn get_voting_power(
+    program_id: Pubkey,
+    time: u64,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: create_read_accounts(vec![stake_account, staking_pool, sysvar::clock::id()])
+            .collect(),
+        data: GetVotingPower { time }.pack(),
+    }
+}
+
+/// Creates `QueryPendingReward` instruction
+pub fn query_pending_reward(
+    program_id: Pubkey,
+    stake_account: Pubkey,
+    staking_pool: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: create_read_accounts(vec![stake_account, staking_pool, sysvar::clock::id()])
+            .collect(),
+        data: QueryPendingReward.pack(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn init_staking_pool(
+        start_time: u64,
+        deposit_before_start: bool,
+        time_basis: TimeBasis,
+        max_pool_size: u64,
+        max_deposit_per_account: u64,
+    ) -> StakingInstruction {
+        StakingInstruction::InitStakingPool {
+            supply: 1_000,
+            duration: 100,
+            earliest_reward_claim_time: 50,
+            bump_seed_staking_program: 255,
+            pool_owner_authority: Pubkey::new_from_array([1; 32]),
+            admin_authority: Pubkey::new_from_array([2; 32]),
+            start_time,
+            deposit_before_start,
+            time_basis,
+            max_pool_size,
+            max_deposit_per_account,
+        }
+    }
+
+    #[test]
+    fn init_staking_pool_round_trip() {
+        let instruction = init_staking_pool(10, true, TimeBasis::UnixTimestamp, 500, 50);
+        assert_eq!(
+            StakingInstruction::unpack(&instruction.pack()),
+            Ok(instruction)
+        );
+    }
+
+    #[test]
+    fn init_staking_pool_defaults_missing_trailing_fields() {
+        let data = init_staking_pool(10, true, TimeBasis::UnixTimestamp, 500, 50).pack();
+        // first layout, with start time, and with time basis
+        let cases = [
+            (90, init_staking_pool(0, false, TimeBasis::Slot, 0, 0)),
+            (99, init_staking_pool(10, true, TimeBasis::Slot, 0, 0)),
+            (
+                100,
+                init_staking_pool(10, true, TimeBasis::UnixTimestamp, 0, 0),
+            ),
+        ];
+        for (len, expected) in cases.iter() {
+            assert_eq!(
+                StakingInstruction::unpack(&data[..*len]).as_ref(),
+                Ok(expected)
+            );
+            // the shorter layouts round trip
+            assert_eq!(expected.pack(), &data[..*len]);
+        }
+    }
+
+    #[test]
+    fn init_staking_pool_builders_pack_both_layouts() {
+        let keys: Vec<_> = (0..7).map(|_| Pubkey::new_unique()).collect();
+        let program_id = Pubkey::new_unique();
+        let first_layout = super::init_staking_pool(
+            program_id, 1_000, 100, 50, keys[0], keys[1], keys[2], keys[3], keys[4], keys[5],
+            keys[6],
+        );
+        let with_options = init_staking_pool_with_options(
+            program_id,
+            1_000,
+            100,
+            50,
+            10,
+            true,
+            TimeBasis::UnixTimestamp,
+            500,
+            50,
+            keys[0],
+            keys[1],
+            keys[2],
+            keys[3],
+            keys[4],
+            keys[5],
+            keys[6],
+        );
+        assert_eq!(first_layout.data.len(), 90);
+        assert_eq!(with_options.data.len(), 116);
+        assert_eq!(first_layout.accounts, with_options.accounts);
+        assert_eq!(first_layout.data[..], with_options.data[..90]);
+        for instruction in [first_layout, with_options].iter() {
+            let unpacked = StakingInstruction::unpack(&instruction.data).unwrap();
+            assert_eq!(unpacked.pack(), instruction.data);
+        }
+    }
+
+    #[test]
+    fn init_staking_pool_rejects_partial_trailing_fields() {
+        let data = init_staking_pool(10, true, TimeBasis::UnixTimestamp, 500, 50).pack();
+        for &len in &[91, 98, 101, 108, 115] {
+            assert_eq!(
+                StakingInstruction::unpack(&data[..len]),
+                Err(StakingError::InstructionUnpackError.into())
+            );
+        }
+        let mut data = data;
+        data.push(0);
+        assert_eq!(
+            StakingInstruction::unpack(&data),
+            Err(StakingError::InstructionUnpackError.into())
+        );
+    }
+
+    #[test]
+    fn split_stake_account_none_reward_amount_is_zero() {
+        let none = StakingInstruction::SplitStakeAccount {
+            amount: 7,
+            reward_amount: None,
+        };
+        let data = none.pack();
+        assert_eq!(data[9..], [0; 9]);
+        assert_eq!(StakingInstruction::unpack(&data), Ok(none));
+
+        let mut data = data;
+        data[10] = 3;
+        assert_eq!(
+            StakingInstruction::unpack(&data),
+            Err(StakingError::InstructionUnpackError.into())
+        );
+        data[9] = 1;
+        assert_eq!(
+            StakingInstruction::unpack(&data),
+            Ok(StakingInstruction::SplitStakeAccount {
+                amount: 7,
+                reward_amount: Some(3),
+            })
+        );
+    }
+}
@@ -1,8 +1,15 @@
 // pub mod state;
 pub use solana_program;
 
+#[cfg(feature = "cpi")]
+pub mod cpi;
 pub mod error;
+pub mod events;
+pub mod explain;
+pub mod idl;
 pub mod instruction;
+pub mod return_data;
+pub mod serialization;
 pub mod state;
 
 //mainnet program id
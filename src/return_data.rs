@@ -0,0 +1,226 @@
+//! Data returned by the staking program with `set_return_data`, for programs invoking it.
+//!
+//! The processor sets the return data of an instruction with `ReturnData::set`; a caller
+//! decodes it with `ReturnData::get` right after the invocation, before invoking any other
+//! program.
+
+use std::convert::TryInto;
+
+use solana_program::program::{get_return_data, set_return_data};
+
+use crate::error::StakingError;
+use crate::solana_program::{msg, program_error::ProgramError, pubkey::Pubkey};
+
+/// Typed return data of a staking instruction, packed as little endian fields.
+pub trait ReturnData: Sized {
+    const LEN: usize;
+
+    fn pack(&self) -> Vec<u8>;
+
+    fn unpack(input: &[u8]) -> Result<Self, ProgramError>;
+
+    /// Set as the return data of the current instruction.
+    fn set(&self) {
+        set_return_data(&self.pack());
+    }
+
+    /// Decode return data as read back from the runtime, which drops its trailing zero bytes.
+    fn unpack_trimmed(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.len() > Self::LEN {
+            return Err(StakingError::InstructionUnpackError.into());
+        }
+        let mut data = input.to_vec();
+        data.resize(Self::LEN, 0);
+        Self::unpack(&data)
+    }
+
+    /// Decode the return data of the last instruction invoked, which must be of `program_id`.
+    fn get(program_id: &Pubkey) -> Result<Self, ProgramError> {
+        match get_return_data() {
+            Some((returning_program, data)) if returning_program == *program_id => {
+                Self::unpack_trimmed(&data)
+            }
+            _ => {
+                msg!("Staking program did not set return data");
+                Err(StakingError::MissingReturnData.into())
+            }
+        }
+    }
+}
+
+/// Returned by `Deposit` and `Withdraw`, the balances after the instruction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StakeBalance {
+    pub deposited_amount: u64,
+    pub pool_size: u64,
+}
+
+/// Returned by `ClaimReward`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClaimedReward {
+    /// Rewards transferred, or topped up to the vesting account in a vesting pool.
+    pub amount: u64,
+    /// Rewards lost to the early claim penalty, not included in `amount`.
+    pub forfeited: u64,
+}
+
+/// Returned by `QueryPendingReward`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PendingReward {
+    /// Unclaimed rewards of the stake account at the current time, without the share of a
+    /// reward distributor not yet pulled into the staking pool.
+    pub reward: u64,
+    /// Part of `reward` a claim at the current time would forfeit, the whole reward when the
+    /// pool rejects claims before its earliest reward claim time.
+    pub penalty: u64,
+}
+
+/// Returned by `GetVotingPower`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VotingPower {
+    pub voting_power: u64,
+}
+
+impl ReturnData for StakeBalance {
+    const LEN: usize = 16;
+
+    fn pack(&self) -> Vec<u8> {
+        [
+            self.deposited_amount.to_le_bytes(),
+            self.pool_size.to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (deposited_amount, rest) = unpack_u64(input)?;
+        let (pool_size, rest) = unpack_u64(rest)?;
+        check_empty(rest)?;
+        Ok(Self {
+            deposited_amount,
+            pool_size,
+        })
+    }
+}
+
+impl ReturnData for ClaimedReward {
+    const LEN: usize = 16;
+
+    fn pack(&self) -> Vec<u8> {
+        [self.amount.to_le_bytes(), self.forfeited.to_le_bytes()].concat()
+    }
+
+    fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (amount, rest) = unpack_u64(input)?;
+        let (forfeited, rest) = unpack_u64(rest)?;
+        check_empty(rest)?;
+        Ok(Self { amount, forfeited })
+    }
+}
+
+impl ReturnData for PendingReward {
+    const LEN: usize = 16;
+
+    fn pack(&self) -> Vec<u8> {
+        [self.reward.to_le_bytes(), self.penalty.to_le_bytes()].concat()
+    }
+
+    fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (reward, rest) = unpack_u64(input)?;
+        let (penalty, rest) = unpack_u64(rest)?;
+        check_empty(rest)?;
+        Ok(Self { reward, penalty })
+    }
+}
+
+impl ReturnData for VotingPower {
+    const LEN: usize = 8;
+
+    fn pack(&self) -> Vec<u8> {
+        self.voting_power.to_le_bytes().to_vec()
+    }
+
+    fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (voting_power, rest) = unpack_u64(input)?;
+        check_empty(rest)?;
+        Ok(Self { voting_power })
+    }
+}
+
+fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+    if input.len() < 8 {
+        return Err(StakingError::InstructionUnpackError.into());
+    }
+    let (bytes, rest) = input.split_at(8);
+    let value = bytes
+        .try_into()
+        .map(u64::from_le_bytes)
+        .map_err(|_| StakingError::InstructionUnpackError)?;
+    Ok((value, rest))
+}
+
+fn check_empty(rest: &[u8]) -> Result<(), ProgramError> {
+    if rest.is_empty() {
+        Ok(())
+    } else {
+        Err(StakingError::InstructionUnpackError.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pack_round_trip() {
+        let balance = StakeBalance {
+            deposited_amount: 5,
+            pool_size: u64::MAX,
+        };
+        assert_eq!(balance.pack().len(), StakeBalance::LEN);
+        assert_eq!(StakeBalance::unpack(&balance.pack()), Ok(balance));
+        let claimed = ClaimedReward {
+            amount: 7,
+            forfeited: 3,
+        };
+        assert_eq!(ClaimedReward::unpack(&claimed.pack()), Ok(claimed));
+        let pending = PendingReward {
+            reward: 9,
+            penalty: 1,
+        };
+        assert_eq!(PendingReward::unpack(&pending.pack()), Ok(pending));
+        let power = VotingPower { voting_power: 11 };
+        assert_eq!(VotingPower::unpack(&power.pack()), Ok(power));
+    }
+
+    #[test]
+    fn unpack_rejects_wrong_length() {
+        let data = StakeBalance::default().pack();
+        assert_eq!(
+            StakeBalance::unpack(&data[..15]),
+            Err(StakingError::InstructionUnpackError.into())
+        );
+        assert_eq!(
+            StakeBalance::unpack(&[data.as_slice(), &[0]].concat()),
+            Err(StakingError::InstructionUnpackError.into())
+        );
+    }
+
+    #[test]
+    fn unpack_trimmed_pads_dropped_zero_bytes() {
+        let claimed = ClaimedReward {
+            amount: 7,
+            forfeited: 0,
+        };
+        // the runtime drops the trailing zero bytes of return data
+        assert_eq!(ClaimedReward::unpack_trimmed(&[7]), Ok(claimed));
+        assert_eq!(
+            ClaimedReward::unpack_trimmed(&[]),
+            Ok(ClaimedReward::default())
+        );
+        assert_eq!(
+            ClaimedReward::unpack_trimmed(&[1; 17]),
+            Err(StakingError::InstructionUnpackError.into())
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Helpers for the optional `serde` and `borsh` representations of the program types.
+
+#[cfg(feature = "serde")]
+pub mod pubkey_base58 {
+    use std::str::FromStr;
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use solana_program::pubkey::Pubkey;
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(pubkey)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Pubkey::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+pub mod decimal_string {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use solana_maths::{Decimal, SCALE};
+
+    pub fn serialize<S: Serializer>(decimal: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(decimal)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse(&s).ok_or_else(|| de::Error::custom(format!("invalid decimal: {}", s)))
+    }
+
+    fn parse(s: &str) -> Option<Decimal> {
+        let (integer, fraction) = match s.find('.') {
+            Some(dot) => (&s[..dot], &s[dot + 1..]),
+            None => (s, ""),
+        };
+        if integer.is_empty()
+            || fraction.len() > SCALE
+            || !integer
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+        let digits = format!("{}{:0<width$}", integer, fraction, width = SCALE);
+        digits.parse::<u128>().ok().map(Decimal::from_scaled_val)
+    }
+}
+
+#[cfg(feature = "borsh")]
+pub(crate) mod decimal_borsh {
+    use std::io::{Error, ErrorKind, Result, Write};
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use solana_maths::Decimal;
+
+    pub fn serialize<W: Write>(decimal: &Decimal, writer: &mut W) -> Result<()> {
+        decimal
+            .to_scaled_val()
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?
+            .serialize(writer)
+    }
+
+    pub fn deserialize(buf: &mut &[u8]) -> Result<Decimal> {
+        u128::deserialize(buf).map(Decimal::from_scaled_val)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    #[cfg(any(feature = "serde", feature = "borsh"))]
+    use solana_maths::Decimal;
+    #[cfg(any(feature = "serde", feature = "borsh"))]
+    use solana_program::pubkey::Pubkey;
+
+    #[cfg(any(feature = "serde", feature = "borsh"))]
+    use crate::instruction::StakingInstruction;
+    #[cfg(any(feature = "serde", feature = "borsh"))]
+    use crate::state::{StakeAccount, StakingPool, TimeBasis, PROGRAM_VERSION};
+
+    #[cfg(any(feature = "serde", feature = "borsh"))]
+    fn init_staking_pool() -> StakingInstruction {
+        StakingInstruction::InitStakingPool {
+            supply: 1_000_000,
+            duration: 1_000,
+            earliest_reward_claim_time: 500,
+            bump_seed_staking_program: 254,
+            pool_owner_authority: Pubkey::new_unique(),
+            admin_authority: Pubkey::new_unique(),
+            start_time: 100,
+            deposit_before_start: true,
+            time_basis: TimeBasis::UnixTimestamp,
+            max_pool_size: 10_000,
+            max_deposit_per_account: 1_000,
+        }
+    }
+
+    #[cfg(any(feature = "serde", feature = "borsh"))]
+    fn staking_pool() -> StakingPool {
+        StakingPool {
+            version: PROGRAM_VERSION,
+            owner_authority: Pubkey::new_unique(),
+            admin_authority: Pubkey::new_unique(),
+            reward_token_pool: Pubkey::new_unique(),
+            last_update: 200,
+            end_time: 1_100,
+            duration: 1_000,
+            rate_per_slot: Decimal::from(1_000u64),
+            cumulative_rate: Decimal::from_scaled_val(1_234_567_890_123_456_789),
+            pool_size: 5_000,
+            time_basis: TimeBasis::UnixTimestamp,
+            custodial: true,
+            ..StakingPool::default()
+        }
+    }
+
+    #[cfg(any(feature = "serde", feature = "borsh"))]
+    fn stake_account() -> StakeAccount {
+        StakeAccount {
+            version: PROGRAM_VERSION,
+            start_rate: Decimal::from_scaled_val(1_234_567_890_123_456_789),
+            owner: Pubkey::new_unique(),
+            pool_pubkey: Pubkey::new_unique(),
+            deposited_amount: 5_000,
+            unclaimed_reward_wads: Decimal::from_scaled_val(42),
+            lock_end: 900,
+            ..StakeAccount::default()
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let instruction = init_staking_pool();
+        let json = serde_json::to_string(&instruction).unwrap();
+        assert_eq!(
+            serde_json::from_str::<StakingInstruction>(&json).unwrap(),
+            instruction
+        );
+
+        let pool = staking_pool();
+        let json = serde_json::to_string(&pool).unwrap();
+        assert_eq!(serde_json::from_str::<StakingPool>(&json).unwrap(), pool);
+
+        let account = stake_account();
+        let json = serde_json::to_value(&account).unwrap();
+        assert_eq!(json["owner"], account.owner.to_string());
+        assert_eq!(json["start_rate"], "1.234567890123456789");
+        assert_eq!(json["unclaimed_reward_wads"], "0.000000000000000042");
+        assert_eq!(
+            serde_json::from_value::<StakeAccount>(json).unwrap(),
+            account
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn rejects_invalid_decimal_strings() {
+        let account = serde_json::to_value(stake_account()).unwrap();
+        for invalid in ["", ".5", "-1", "1e3", "0.0000000000000000001"] {
+            let mut json = account.clone();
+            json["start_rate"] = invalid.into();
+            assert!(
+                serde_json::from_value::<StakeAccount>(json).is_err(),
+                "{:?}",
+                invalid
+            );
+        }
+        let mut json = account;
+        json["start_rate"] = "7".into();
+        assert_eq!(
+            serde_json::from_value::<StakeAccount>(json)
+                .unwrap()
+                .start_rate,
+            Decimal::from(7u64)
+        );
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn borsh_round_trip() {
+        use borsh::{BorshDeserialize, BorshSerialize};
+
+        let instruction = init_staking_pool();
+        let data = instruction.try_to_vec().unwrap();
+        assert_eq!(
+            StakingInstruction::try_from_slice(&data).unwrap(),
+            instruction
+        );
+
+        let pool = staking_pool();
+        let data = pool.try_to_vec().unwrap();
+        assert_eq!(StakingPool::try_from_slice(&data).unwrap(), pool);
+
+        let account = stake_account();
+        let data = account.try_to_vec().unwrap();
+        assert_eq!(StakeAccount::try_from_slice(&data).unwrap(), account);
+        assert!(StakeAccount::try_from_slice(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::pubkey::PUBKEY_BYTES;
+
+use crate::solana_program::program_error::ProgramError;
+use crate::solana_program::program_pack::{IsInitialized, Pack, Sealed};
+use crate::solana_program::{msg, pubkey::Pubkey};
+use crate::state::{PROGRAM_VERSION, UNINITIALIZED_VERSION};
+
+/// Seed prefix of allowlist entry addresses, followed by the staking pool and the owner.
+pub const ALLOWLIST_SEED: &[u8] = b"allowlist";
+
+/// Address of the allowlist entry of `owner` in `staking_pool`, with its bump seed.
+pub fn find_allowlist_entry_address(
+    program_id: &Pubkey,
+    staking_pool: &Pubkey,
+    owner: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[ALLOWLIST_SEED, staking_pool.as_ref(), owner.as_ref()],
+        program_id,
+    )
+}
+
+/// Proof that `owner` may create stake accounts in an allowlisted staking pool.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct AllowlistEntry {
+    /// Version of the struct
+    pub version: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub staking_pool: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub owner: Pubkey,
+    pub bump_seed: u8,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub reserve_fields: [u8; 32],
+}
+
+impl AllowlistEntry {
+    /// Whether this entry allows `owner` into `staking_pool`.
+    pub fn allows(&self, staking_pool: &Pubkey, owner: &Pubkey) -> bool {
+        self.is_initialized() && self.staking_pool == *staking_pool && self.owner == *owner
+    }
+}
+
+impl Sealed for AllowlistEntry {}
+impl IsInitialized for AllowlistEntry {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+impl Pack for AllowlistEntry {
+    const LEN: usize = 1 + PUBKEY_BYTES + PUBKEY_BYTES + 1 + 32;
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let output = array_mut_ref![dst, 0, AllowlistEntry::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (version, staking_pool, owner, bump_seed, _) =
+            mut_array_refs![output, 1, PUBKEY_BYTES, PUBKEY_BYTES, 1, 32];
+        *version = self.version.to_le_bytes();
+        staking_pool.copy_from_slice(self.staking_pool.as_ref());
+        owner.copy_from_slice(self.owner.as_ref());
+        *bump_seed = self.bump_seed.to_le_bytes();
+    }
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![src, 0, AllowlistEntry::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (version, staking_pool, owner, bump_seed, _) =
+            array_refs![input, 1, PUBKEY_BYTES, PUBKEY_BYTES, 1, 32];
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("allowlist entry version does not match staking program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Self {
+            version,
+            staking_pool: Pubkey::new_from_array(*staking_pool),
+            owner: Pubkey::new_from_array(*owner),
+            bump_seed: u8::from_le_bytes(*bump_seed),
+            reserve_fields: [0; 32],
+        })
+    }
+}
@@ -0,0 +1,36 @@
+//! Accounts shared by the state tests.
+
+use solana_maths::Decimal;
+
+use crate::solana_program::pubkey::Pubkey;
+use crate::state::{StakeAccount, StakingPool, PROGRAM_VERSION};
+
+/// Staking pool emitting 10 per slot from 0 to 1,000 to `pool_size` staked.
+pub fn staking_pool(pool_size: u64) -> StakingPool {
+    StakingPool {
+        version: PROGRAM_VERSION,
+        end_time: 1_000,
+        duration: 1_000,
+        rate_per_slot: Decimal::from(10u64),
+        pool_size,
+        owed_reward_tracked: true,
+        ..StakingPool::default()
+    }
+}
+
+/// Stake account settled at the start of `staking_pool` holding `deposited_amount`.
+pub fn stake_account(deposited_amount: u64) -> StakeAccount {
+    StakeAccount {
+        version: PROGRAM_VERSION,
+        deposited_amount,
+        ..StakeAccount::default()
+    }
+}
+
+/// `stake_account` of `owner`.
+pub fn owned_stake_account(owner: Pubkey, deposited_amount: u64) -> StakeAccount {
+    StakeAccount {
+        owner,
+        ..stake_account(deposited_amount)
+    }
+}
@@ -0,0 +1,711 @@
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::clock::Slot;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::pubkey::PUBKEY_BYTES;
+use std::convert::{TryFrom, TryInto};
+
+use crate::error::StakingError;
+use crate::solana_program::program_error::ProgramError;
+use crate::solana_program::program_pack::{IsInitialized, Pack, Sealed};
+use crate::solana_program::{msg, pubkey::Pubkey};
+use crate::state::{StakeAccount, TimeBasis, MAX_BPS, PROGRAM_VERSION, UNINITIALIZED_VERSION};
+use solana_maths::{Decimal, TryDiv};
+
+/// Maximum number of staking pools a gauge can split emissions between.
+pub const MAX_GAUGE_POOLS: usize = 16;
+
+/// Seed prefix of gauge vote addresses, followed by the gauge and the stake account.
+pub const GAUGE_VOTE_SEED: &[u8] = b"gauge_vote";
+
+/// Address of the vote of `stake_account` on `gauge`, with its bump seed.
+pub fn find_gauge_vote_address(
+    program_id: &Pubkey,
+    gauge: &Pubkey,
+    stake_account: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[GAUGE_VOTE_SEED, gauge.as_ref(), stake_account.as_ref()],
+        program_id,
+    )
+}
+
+/// Staking pool receiving a share of the emissions of a gauge.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct GaugePool {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub staking_pool: Pubkey,
+    /// voting power committed to the pool in the current epoch
+    pub votes: u64,
+}
+
+impl GaugePool {
+    const LEN: usize = PUBKEY_BYTES + 8;
+}
+
+/// Splits `emission_per_epoch` between its staking pools by the votes of the stakers of
+/// `governance_pool`. The staking pools must have the gauge derived address as admin
+/// authority so the epoch crank can set their `rate_per_slot`.
+///
+/// Emissions are funded from `reward_token_vault`, owned by the gauge derived address: the
+/// epoch crank transfers the share of each staking pool into its reward token pool and sets
+/// the rate emitting exactly that share until the end of the epoch, so the pools never owe
+/// more than they hold. The crank fails while the vault holds less than the emission.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct Gauge {
+    /// Version of the struct
+    pub version: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub admin_authority: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub governance_pool: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub reward_token_vault: Pubkey,
+    pub bump_seed: u8,
+    pub time_basis: TimeBasis,
+    pub epoch_duration: u64,
+    // starts at 1
+    pub epoch: u64,
+    pub epoch_start: Slot,
+    pub emission_per_epoch: u64,
+    pub pools: Vec<GaugePool>,
+}
+
+impl Gauge {
+    /// Check that a staking pool keeping time in `time_basis` can vote in or be fed by this
+    /// gauge.
+    pub fn check_time_basis(&self, time_basis: TimeBasis) -> ProgramResult {
+        if time_basis != self.time_basis {
+            msg!("Staking pool and gauge have different time bases");
+            return Err(StakingError::TimeBasisMismatch.into());
+        }
+        Ok(())
+    }
+
+    /// End of the current epoch.
+    pub fn epoch_end(&self) -> Result<Slot, ProgramError> {
+        self.epoch_start
+            .checked_add(self.epoch_duration)
+            .ok_or_else(|| StakingError::MathOverflow.into())
+    }
+
+    /// Register `staking_pool`, keeping time in `time_basis`, to receive a share of the
+    /// emissions from the next epoch.
+    pub fn add_pool(&mut self, staking_pool: &Pubkey, time_basis: TimeBasis) -> ProgramResult {
+        self.check_time_basis(time_basis)?;
+        if self
+            .pools
+            .iter()
+            .any(|pool| pool.staking_pool == *staking_pool)
+        {
+            msg!("Staking pool is already in the gauge");
+            return Err(StakingError::InvalidStakingPool.into());
+        }
+        if self.pools.len() >= MAX_GAUGE_POOLS {
+            msg!("Gauge cannot split emissions between more staking pools");
+            return Err(StakingError::GaugeFull.into());
+        }
+        self.pools.push(GaugePool {
+            staking_pool: *staking_pool,
+            votes: 0,
+        });
+        Ok(())
+    }
+
+    /// Add `voting_power` of `stake_account` split by the weights of `vote` to the tally of the
+    /// current epoch, once per epoch and before its end. `gauge` and `stake_account_key` are
+    /// the addresses of this gauge and the stake account, and `authority` the signer, which
+    /// must own the stake account. The deposit of the stake account cannot be withdrawn, merged
+    /// or split until the end of the epoch, so it cannot vote twice.
+    #[allow(clippy::too_many_arguments)]
+    pub fn commit_vote(
+        &mut self,
+        gauge: &Pubkey,
+        vote: &mut GaugeVote,
+        stake_account_key: &Pubkey,
+        stake_account: &mut StakeAccount,
+        authority: &Pubkey,
+        voting_power: u64,
+        current_time: Slot,
+    ) -> ProgramResult {
+        if stake_account.owner != *authority {
+            msg!("Gauge vote can only be committed by the stake account owner");
+            return Err(StakingError::InvalidSigner.into());
+        }
+        if stake_account.pool_pubkey != self.governance_pool {
+            msg!("Stake account is not in the governance staking pool of the gauge");
+            return Err(StakingError::InvalidStakeAccount.into());
+        }
+        if vote.gauge != *gauge || vote.stake_account != *stake_account_key {
+            msg!("Gauge vote is not the vote of the stake account on the gauge");
+            return Err(StakingError::InvalidArgumentError.into());
+        }
+        if vote.weights_bps.iter().all(|weight_bps| *weight_bps == 0) {
+            msg!("Gauge vote has no weight");
+            return Err(StakingError::InvalidArgumentError.into());
+        }
+        if vote.committed_epoch >= self.epoch {
+            msg!("Gauge vote is already committed in this epoch");
+            return Err(StakingError::GaugeVoteAlreadyCommitted.into());
+        }
+        let epoch_end = self.epoch_end()?;
+        if current_time >= epoch_end {
+            msg!("Gauge epoch has ended");
+            return Err(StakingError::GaugeEpochEnded.into());
+        }
+        for (pool, weight_bps) in self.pools.iter_mut().zip(vote.weights_bps.iter()) {
+            let votes = voting_power as u128 * *weight_bps as u128 / MAX_BPS as u128;
+            pool.votes = pool
+                .votes
+                .checked_add(votes as u64)
+                .ok_or(StakingError::MathOverflow)?;
+        }
+        vote.committed_epoch = self.epoch;
+        stake_account.voted_until = stake_account.voted_until.max(epoch_end);
+        Ok(())
+    }
+
+    /// Close the epoch ended at `current_time`, returning the reward tokens each staking pool,
+    /// in the order of `pools`, emits in the next epoch, at most `emission_per_epoch` in total.
+    /// The crank transfers them from the reward token vault and sets the staking pools to emit
+    /// them at `emission_rate` until `epoch_end`.
+    pub fn advance_epoch(&mut self, current_time: Slot) -> Result<Vec<u64>, ProgramError> {
+        if current_time < self.epoch_end()? {
+            msg!("Gauge epoch has not ended");
+            return Err(StakingError::GaugeEpochNotEnded.into());
+        }
+        let total_votes = self
+            .pools
+            .iter()
+            .try_fold(0u64, |total, pool| total.checked_add(pool.votes))
+            .ok_or(StakingError::MathOverflow)?;
+        let amounts = self
+            .pools
+            .iter()
+            .map(|pool| {
+                if total_votes == 0 {
+                    return Ok(0);
+                }
+                let amount =
+                    self.emission_per_epoch as u128 * pool.votes as u128 / total_votes as u128;
+                u64::try_from(amount).map_err(|_| StakingError::MathOverflow.into())
+            })
+            .collect::<Result<Vec<_>, ProgramError>>()?;
+        for pool in self.pools.iter_mut() {
+            pool.votes = 0;
+        }
+        self.epoch += 1;
+        self.epoch_start = current_time;
+        Ok(amounts)
+    }
+
+    /// `rate_per_slot` emitting `amount` over an epoch.
+    pub fn emission_rate(&self, amount: u64) -> Result<Decimal, ProgramError> {
+        Decimal::from(amount).try_div(self.epoch_duration)
+    }
+}
+
+/// Split of the voting power of a stake account between the staking pools of a gauge.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct GaugeVote {
+    /// Version of the struct
+    pub version: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub gauge: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub stake_account: Pubkey,
+    pub bump_seed: u8,
+    // last epoch the vote was counted in
+    pub committed_epoch: u64,
+    // by index in `Gauge::pools`, summing up to at most 10000
+    pub weights_bps: [u16; MAX_GAUGE_POOLS],
+}
+
+impl GaugeVote {
+    /// Set the weight of the staking pool at `pool_index` in the gauge.
+    pub fn set_weight(&mut self, pool_index: u8, weight_bps: u16) -> ProgramResult {
+        let pool_index = pool_index as usize;
+        if pool_index >= MAX_GAUGE_POOLS {
+            msg!("Gauge pool index is out of range");
+            return Err(StakingError::InvalidArgumentError.into());
+        }
+        let total = self
+            .weights_bps
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| if i == pool_index { weight_bps } else { *weight } as u32)
+            .sum::<u32>();
+        if total > MAX_BPS as u32 {
+            msg!("Gauge vote weights must add up to at most 10000 basis points");
+            return Err(StakingError::InvalidArgumentError.into());
+        }
+        self.weights_bps[pool_index] = weight_bps;
+        Ok(())
+    }
+}
+
+impl Sealed for Gauge {}
+impl IsInitialized for Gauge {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+impl Pack for Gauge {
+    const LEN: usize = 1
+        + PUBKEY_BYTES
+        + PUBKEY_BYTES
+        + PUBKEY_BYTES
+        + 1
+        + 1
+        + 8
+        + 8
+        + 8
+        + 8
+        + 1
+        + GaugePool::LEN * MAX_GAUGE_POOLS
+        + 32;
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let output = array_mut_ref![dst, 0, Gauge::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            admin_authority,
+            governance_pool,
+            reward_token_vault,
+            bump_seed,
+            time_basis,
+            epoch_duration,
+            epoch,
+            epoch_start,
+            emission_per_epoch,
+            pools_len,
+            pools_flat,
+            _,
+        ) = mut_array_refs![
+            output,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            1,
+            1,
+            8,
+            8,
+            8,
+            8,
+            1,
+            GaugePool::LEN * MAX_GAUGE_POOLS,
+            32
+        ];
+        *version = self.version.to_le_bytes();
+        admin_authority.copy_from_slice(self.admin_authority.as_ref());
+        governance_pool.copy_from_slice(self.governance_pool.as_ref());
+        reward_token_vault.copy_from_slice(self.reward_token_vault.as_ref());
+        *bump_seed = self.bump_seed.to_le_bytes();
+        *time_basis = [self.time_basis as u8];
+        *epoch_duration = self.epoch_duration.to_le_bytes();
+        *epoch = self.epoch.to_le_bytes();
+        *epoch_start = self.epoch_start.to_le_bytes();
+        *emission_per_epoch = self.emission_per_epoch.to_le_bytes();
+        *pools_len = (self.pools.len() as u8).to_le_bytes();
+
+        pools_flat.fill(0);
+        for (pool, dst) in self
+            .pools
+            .iter()
+            .zip(pools_flat.chunks_exact_mut(GaugePool::LEN))
+        {
+            let dst = array_mut_ref![dst, 0, GaugePool::LEN];
+            #[allow(clippy::ptr_offset_with_cast)]
+            let (staking_pool, votes) = mut_array_refs![dst, PUBKEY_BYTES, 8];
+            staking_pool.copy_from_slice(pool.staking_pool.as_ref());
+            *votes = pool.votes.to_le_bytes();
+        }
+    }
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![src, 0, Gauge::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            admin_authority,
+            governance_pool,
+            reward_token_vault,
+            bump_seed,
+            time_basis,
+            epoch_duration,
+            epoch,
+            epoch_start,
+            emission_per_epoch,
+            pools_len,
+            pools_flat,
+            _,
+        ) = array_refs![
+            input,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            1,
+            1,
+            8,
+            8,
+            8,
+            8,
+            1,
+            GaugePool::LEN * MAX_GAUGE_POOLS,
+            32
+        ];
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("gauge version does not match staking program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let pools_len = u8::from_le_bytes(*pools_len) as usize;
+        if pools_len > MAX_GAUGE_POOLS {
+            msg!("gauge splits emissions between too many staking pools");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let pools = pools_flat
+            .chunks_exact(GaugePool::LEN)
+            .take(pools_len)
+            .map(|src| {
+                let src = array_ref![src, 0, GaugePool::LEN];
+                #[allow(clippy::ptr_offset_with_cast)]
+                let (staking_pool, votes) = array_refs![src, PUBKEY_BYTES, 8];
+                GaugePool {
+                    staking_pool: Pubkey::new_from_array(*staking_pool),
+                    votes: u64::from_le_bytes(*votes),
+                }
+            })
+            .collect();
+        Ok(Self {
+            version,
+            admin_authority: Pubkey::new_from_array(*admin_authority),
+            governance_pool: Pubkey::new_from_array(*governance_pool),
+            reward_token_vault: Pubkey::new_from_array(*reward_token_vault),
+            bump_seed: u8::from_le_bytes(*bump_seed),
+            time_basis: TimeBasis::try_from(u8::from_le_bytes(*time_basis))?,
+            epoch_duration: u64::from_le_bytes(*epoch_duration),
+            epoch: u64::from_le_bytes(*epoch),
+            epoch_start: Slot::from_le_bytes(*epoch_start),
+            emission_per_epoch: u64::from_le_bytes(*emission_per_epoch),
+            pools,
+        })
+    }
+}
+
+impl Sealed for GaugeVote {}
+impl IsInitialized for GaugeVote {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+impl Pack for GaugeVote {
+    const LEN: usize = 1 + PUBKEY_BYTES + PUBKEY_BYTES + 1 + 8 + 2 * MAX_GAUGE_POOLS + 32;
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let output = array_mut_ref![dst, 0, GaugeVote::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (version, gauge, stake_account, bump_seed, committed_epoch, weights_bps, _) = mut_array_refs![
+            output,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            1,
+            8,
+            2 * MAX_GAUGE_POOLS,
+            32
+        ];
+        *version = self.version.to_le_bytes();
+        gauge.copy_from_slice(self.gauge.as_ref());
+        stake_account.copy_from_slice(self.stake_account.as_ref());
+        *bump_seed = self.bump_seed.to_le_bytes();
+        *committed_epoch = self.committed_epoch.to_le_bytes();
+        for (weight, dst) in self.weights_bps.iter().zip(weights_bps.chunks_exact_mut(2)) {
+            dst.copy_from_slice(&weight.to_le_bytes());
+        }
+    }
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![src, 0, GaugeVote::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (version, gauge, stake_account, bump_seed, committed_epoch, weights_flat, _) = array_refs![
+            input,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            1,
+            8,
+            2 * MAX_GAUGE_POOLS,
+            32
+        ];
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("gauge vote version does not match staking program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut weights_bps = [0; MAX_GAUGE_POOLS];
+        for (weight, src) in weights_bps.iter_mut().zip(weights_flat.chunks_exact(2)) {
+            *weight = u16::from_le_bytes(
+                src.try_into()
+                    .map_err(|_| ProgramError::InvalidAccountData)?,
+            );
+        }
+        Ok(Self {
+            version,
+            gauge: Pubkey::new_from_array(*gauge),
+            stake_account: Pubkey::new_from_array(*stake_account),
+            bump_seed: u8::from_le_bytes(*bump_seed),
+            committed_epoch: u64::from_le_bytes(*committed_epoch),
+            weights_bps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::state::fixtures::stake_account;
+    use solana_maths::{TryAdd, TryMul};
+
+    fn gauge(pools: usize) -> Gauge {
+        Gauge {
+            version: PROGRAM_VERSION,
+            time_basis: TimeBasis::Slot,
+            epoch_duration: 100,
+            epoch: 1,
+            emission_per_epoch: 1_000,
+            pools: (0..pools)
+                .map(|_| GaugePool {
+                    staking_pool: Pubkey::new_unique(),
+                    votes: 0,
+                })
+                .collect(),
+            ..Gauge::default()
+        }
+    }
+
+    fn gauge_vote(weights: &[u16]) -> GaugeVote {
+        let mut vote = GaugeVote {
+            version: PROGRAM_VERSION,
+            ..GaugeVote::default()
+        };
+        vote.weights_bps[..weights.len()].copy_from_slice(weights);
+        vote
+    }
+
+    // fixtures are all for the default gauge, stake account and owner
+    fn commit(
+        gauge: &mut Gauge,
+        vote: &mut GaugeVote,
+        stake_account: &mut StakeAccount,
+        voting_power: u64,
+        current_time: Slot,
+    ) -> ProgramResult {
+        let owner = stake_account.owner;
+        gauge.commit_vote(
+            &Pubkey::default(),
+            vote,
+            &Pubkey::default(),
+            stake_account,
+            &owner,
+            voting_power,
+            current_time,
+        )
+    }
+
+    #[test]
+    fn add_pool_rejects_duplicates_and_other_time_basis() {
+        let mut gauge = gauge(0);
+        let staking_pool = Pubkey::new_unique();
+        gauge.add_pool(&staking_pool, TimeBasis::Slot).unwrap();
+        assert_eq!(gauge.pools.len(), 1);
+        assert_eq!(
+            gauge.add_pool(&staking_pool, TimeBasis::Slot),
+            Err(StakingError::InvalidStakingPool.into())
+        );
+        assert_eq!(
+            gauge.add_pool(&Pubkey::new_unique(), TimeBasis::UnixTimestamp),
+            Err(StakingError::TimeBasisMismatch.into())
+        );
+    }
+
+    #[test]
+    fn add_pool_rejects_full_gauge() {
+        let mut gauge = gauge(MAX_GAUGE_POOLS);
+        assert_eq!(
+            gauge.add_pool(&Pubkey::new_unique(), TimeBasis::Slot),
+            Err(StakingError::GaugeFull.into())
+        );
+    }
+
+    #[test]
+    fn commit_vote_splits_voting_power_and_locks_stake_account() {
+        let mut gauge = gauge(2);
+        let mut vote = gauge_vote(&[7_500, 2_500]);
+        let mut stake_account = stake_account(100);
+        commit(&mut gauge, &mut vote, &mut stake_account, 1_000, 40).unwrap();
+
+        assert_eq!(gauge.pools[0].votes, 750);
+        assert_eq!(gauge.pools[1].votes, 250);
+        assert_eq!(vote.committed_epoch, 1);
+        assert_eq!(stake_account.voted_until, 100);
+        assert_eq!(
+            stake_account.check_unlocked(99),
+            Err(StakingError::StakeLocked.into())
+        );
+        assert_eq!(stake_account.check_unlocked(100), Ok(()));
+    }
+
+    #[test]
+    fn commit_vote_once_per_epoch_before_its_end() {
+        let mut gauge = gauge(1);
+        let mut vote = gauge_vote(&[10_000]);
+        let mut stake_account = stake_account(100);
+        commit(&mut gauge, &mut vote, &mut stake_account, 1_000, 40).unwrap();
+        assert_eq!(
+            commit(&mut gauge, &mut vote, &mut stake_account, 1_000, 50),
+            Err(StakingError::GaugeVoteAlreadyCommitted.into())
+        );
+
+        let mut other_vote = gauge_vote(&[10_000]);
+        assert_eq!(
+            commit(&mut gauge, &mut other_vote, &mut stake_account, 1_000, 100),
+            Err(StakingError::GaugeEpochEnded.into())
+        );
+        assert_eq!(gauge.pools[0].votes, 1_000);
+    }
+
+    #[test]
+    fn commit_vote_only_by_owner_of_voting_stake_account() {
+        let mut gauge = gauge(1);
+        let mut vote = gauge_vote(&[10_000]);
+        let mut stake_account = stake_account(100);
+
+        let not_owner = Pubkey::new_unique();
+        assert_eq!(
+            gauge.commit_vote(
+                &Pubkey::default(),
+                &mut vote,
+                &Pubkey::default(),
+                &mut stake_account,
+                &not_owner,
+                1_000,
+                40
+            ),
+            Err(StakingError::InvalidSigner.into())
+        );
+
+        let mut other_pool_account = StakeAccount {
+            pool_pubkey: Pubkey::new_unique(),
+            ..stake_account.clone()
+        };
+        assert_eq!(
+            commit(&mut gauge, &mut vote, &mut other_pool_account, 1_000, 40),
+            Err(StakingError::InvalidStakeAccount.into())
+        );
+
+        let mut other_vote = GaugeVote {
+            stake_account: Pubkey::new_unique(),
+            ..vote.clone()
+        };
+        assert_eq!(
+            commit(&mut gauge, &mut other_vote, &mut stake_account, 1_000, 40),
+            Err(StakingError::InvalidArgumentError.into())
+        );
+
+        let mut empty_vote = gauge_vote(&[]);
+        assert_eq!(
+            commit(&mut gauge, &mut empty_vote, &mut stake_account, 1_000, 40),
+            Err(StakingError::InvalidArgumentError.into())
+        );
+        assert_eq!(gauge.pools[0].votes, 0);
+        assert_eq!(stake_account.voted_until, 0);
+    }
+
+    #[test]
+    fn advance_epoch_splits_emission_by_votes() {
+        let mut gauge = gauge(2);
+        gauge.pools[0].votes = 300;
+        gauge.pools[1].votes = 100;
+        assert_eq!(
+            gauge.advance_epoch(99),
+            Err(StakingError::GaugeEpochNotEnded.into())
+        );
+
+        assert_eq!(gauge.advance_epoch(120), Ok(vec![750, 250]));
+        assert_eq!(gauge.emission_rate(750), Decimal::from(750u64).try_div(100));
+        assert_eq!(gauge.epoch, 2);
+        assert_eq!(gauge.epoch_start, 120);
+        assert_eq!(gauge.epoch_end(), Ok(220));
+        assert!(gauge.pools.iter().all(|pool| pool.votes == 0));
+
+        assert_eq!(gauge.advance_epoch(220), Ok(vec![0, 0]));
+    }
+
+    #[test]
+    fn advance_epoch_never_splits_more_than_emission() {
+        let mut gauge = gauge(3);
+        for pool in gauge.pools.iter_mut() {
+            pool.votes = 1;
+        }
+
+        // the rounding dust stays in the reward token vault
+        let amounts = gauge.advance_epoch(100).unwrap();
+        assert_eq!(amounts, vec![333, 333, 333]);
+        let emitted = amounts
+            .iter()
+            .map(|amount| gauge.emission_rate(*amount).unwrap().try_mul(100).unwrap())
+            .fold(Decimal::zero(), |total, emitted| {
+                total.try_add(emitted).unwrap()
+            });
+        assert!(emitted <= Decimal::from(gauge.emission_per_epoch));
+    }
+
+    #[test]
+    fn pack_round_trip() {
+        let mut gauge = gauge(3);
+        gauge.admin_authority = Pubkey::new_unique();
+        gauge.governance_pool = Pubkey::new_unique();
+        gauge.reward_token_vault = Pubkey::new_unique();
+        gauge.pools[1].votes = 42;
+        let mut data = vec![0; Gauge::LEN];
+        Gauge::pack(gauge.clone(), &mut data).unwrap();
+        assert_eq!(Gauge::unpack(&data), Ok(gauge));
+
+        let mut vote = gauge_vote(&[1_000, 9_000]);
+        vote.committed_epoch = 7;
+        let mut data = vec![0; GaugeVote::LEN];
+        GaugeVote::pack(vote.clone(), &mut data).unwrap();
+        assert_eq!(GaugeVote::unpack(&data), Ok(vote));
+    }
+
+    #[test]
+    fn set_weight_caps_total_weight() {
+        let mut vote = gauge_vote(&[6_000]);
+        vote.set_weight(1, 4_000).unwrap();
+        vote.set_weight(0, 5_000).unwrap();
+        assert_eq!(
+            vote.set_weight(1, 5_001),
+            Err(StakingError::InvalidArgumentError.into())
+        );
+        assert_eq!(
+            vote.set_weight(MAX_GAUGE_POOLS as u8, 0),
+            Err(StakingError::InvalidArgumentError.into())
+        );
+        assert_eq!(&vote.weights_bps[..2], &[5_000, 4_000]);
+    }
+}
@@ -1,11 +1,34 @@
+pub use allowlist_entry::*;
+pub use gauge::*;
+pub use reward_distributor::*;
 pub use stake_account::*;
 pub use staking_pool::*;
+pub use vesting_account::*;
 
+use crate::solana_program::{msg, program_error::ProgramError};
+
+pub mod allowlist_entry;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod gauge;
+pub mod reward_distributor;
 pub mod stake_account;
 pub mod staking_pool;
+pub mod vesting_account;
 
 /// Current version of the program and all new accounts created
 pub const PROGRAM_VERSION: u8 = 1;
 /// Accounts are created with data zeroed out, so uninitialized state instances
 /// will have the version set to 0.
 pub const UNINITIALIZED_VERSION: u8 = 0;
+
+pub(crate) fn unpack_bool(src: &[u8; 1]) -> Result<bool, ProgramError> {
+    match u8::from_le_bytes(*src) {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => {
+            msg!("Boolean cannot be unpacked");
+            Err(ProgramError::InvalidAccountData)
+        }
+    }
+}
@@ -0,0 +1,539 @@
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::clock::Slot;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::pubkey::PUBKEY_BYTES;
+use std::cmp::min;
+use std::convert::TryFrom;
+
+use crate::error::StakingError;
+#[cfg(feature = "borsh")]
+use crate::serialization::decimal_borsh;
+use crate::solana_program::program_error::ProgramError;
+use crate::solana_program::program_pack::{IsInitialized, Pack, Sealed};
+use crate::solana_program::{msg, pubkey::Pubkey};
+use crate::state::{TimeBasis, PROGRAM_VERSION, UNINITIALIZED_VERSION};
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_maths::{Decimal, TryAdd, TryDiv, TryMul, TrySub};
+
+/// Maximum number of staking pools a reward distributor can feed.
+pub const MAX_DISTRIBUTOR_POOLS: usize = 16;
+
+/// Share of a staking pool in the emissions of a reward distributor.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DistributorPool {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub staking_pool: Pubkey,
+    pub weight: u64,
+    /// `reward_per_weight` when the pool last accrued
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
+    pub checkpoint: Decimal,
+    /// rewards accrued to the pool and not pulled yet
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
+    pub accrued_reward_wads: Decimal,
+}
+
+impl DistributorPool {
+    const LEN: usize = PUBKEY_BYTES + 8 + Decimal::LEN + Decimal::LEN;
+
+    fn accrue(&mut self, reward_per_weight: Decimal) -> ProgramResult {
+        let reward = reward_per_weight
+            .try_sub(self.checkpoint)?
+            .try_mul(self.weight)?;
+        self.accrued_reward_wads = self.accrued_reward_wads.try_add(reward)?;
+        self.checkpoint = reward_per_weight;
+        Ok(())
+    }
+}
+
+/// Emits `rate_per_slot` reward tokens from its vault to the registered staking pools in
+/// proportion to their weight. Pools pull their share only in `RefreshStakeAccount` with the
+/// distributor accounts, which clients send before settling stake accounts of a fed pool.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RewardDistributor {
+    /// Version of the struct
+    pub version: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub admin_authority: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub reward_token_vault: Pubkey,
+    pub bump_seed: u8,
+    // unit of every time below, must match the fed staking pools
+    pub time_basis: TimeBasis,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
+    pub rate_per_slot: Decimal,
+    // cumulative emissions per unit of weight
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
+    pub reward_per_weight: Decimal,
+    // emissions while no pool had weight
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
+    pub unallocated_reward_wads: Decimal,
+    pub last_update: Slot,
+    pub end_time: Slot,
+    pub total_weight: u64,
+    pub pools: Vec<DistributorPool>,
+}
+
+impl RewardDistributor {
+    /// Accrue the emissions up to `current_time` to the pools.
+    pub fn update(&mut self, current_time: Slot) -> ProgramResult {
+        if current_time < self.last_update {
+            msg!("Current time is earlier than the last reward distributor update");
+            return Err(StakingError::InvalidCurrentTimeError.into());
+        }
+        let current_time = min(current_time, self.end_time);
+        if current_time <= self.last_update {
+            // after the end of the emissions
+            return Ok(());
+        }
+        let reward = self
+            .rate_per_slot
+            .try_mul(current_time - self.last_update)?;
+        if self.total_weight != 0 {
+            self.reward_per_weight = self
+                .reward_per_weight
+                .try_add(reward.try_div(self.total_weight)?)?;
+        } else {
+            self.unallocated_reward_wads = self.unallocated_reward_wads.try_add(reward)?;
+        }
+        self.last_update = current_time;
+        Ok(())
+    }
+
+    /// Check that a staking pool keeping time in `time_basis` can be fed by this distributor.
+    pub fn check_time_basis(&self, time_basis: TimeBasis) -> ProgramResult {
+        if time_basis != self.time_basis {
+            msg!("Staking pool and reward distributor have different time bases");
+            return Err(StakingError::TimeBasisMismatch.into());
+        }
+        Ok(())
+    }
+
+    fn find_pool_mut(&mut self, staking_pool: &Pubkey) -> Option<&mut DistributorPool> {
+        self.pools
+            .iter_mut()
+            .find(|pool| pool.staking_pool == *staking_pool)
+    }
+
+    /// Set the weight of `staking_pool`, keeping time in `time_basis`, from `current_time`,
+    /// registering it if needed. Pools set to 0 are removed once they have pulled all of their
+    /// whole rewards, the rest returns to `unallocated_reward_wads`.
+    pub fn set_weight(
+        &mut self,
+        staking_pool: &Pubkey,
+        time_basis: TimeBasis,
+        weight: u64,
+        current_time: Slot,
+    ) -> ProgramResult {
+        self.check_time_basis(time_basis)?;
+        self.update(current_time)?;
+        let reward_per_weight = self.reward_per_weight;
+        let previous_weight = match self.find_pool_mut(staking_pool) {
+            Some(pool) => {
+                pool.accrue(reward_per_weight)?;
+                let previous_weight = pool.weight;
+                pool.weight = weight;
+                previous_weight
+            }
+            None => {
+                if self.pools.len() >= MAX_DISTRIBUTOR_POOLS {
+                    msg!("Reward distributor cannot feed more staking pools");
+                    return Err(StakingError::DistributorFull.into());
+                }
+                self.pools.push(DistributorPool {
+                    staking_pool: *staking_pool,
+                    weight,
+                    checkpoint: reward_per_weight,
+                    accrued_reward_wads: Decimal::zero(),
+                });
+                0
+            }
+        };
+        self.total_weight = self
+            .total_weight
+            .checked_sub(previous_weight)
+            .and_then(|total_weight| total_weight.checked_add(weight))
+            .ok_or(StakingError::MathOverflow)?;
+        let is_removed =
+            |pool: &DistributorPool| pool.weight == 0 && pool.accrued_reward_wads < Decimal::one();
+        for pool in self.pools.iter().filter(|pool| is_removed(pool)) {
+            self.unallocated_reward_wads = self
+                .unallocated_reward_wads
+                .try_add(pool.accrued_reward_wads)?;
+        }
+        self.pools.retain(|pool| !is_removed(pool));
+        Ok(())
+    }
+
+    /// Accrue emissions up to `current_time` and take the whole tokens owed to `staking_pool`,
+    /// keeping time in `time_basis`, returning the amount to transfer to its reward token pool.
+    pub fn pull(
+        &mut self,
+        staking_pool: &Pubkey,
+        time_basis: TimeBasis,
+        current_time: Slot,
+    ) -> Result<u64, ProgramError> {
+        self.check_time_basis(time_basis)?;
+        self.update(current_time)?;
+        let reward_per_weight = self.reward_per_weight;
+        let pool = self.find_pool_mut(staking_pool).ok_or_else(|| {
+            msg!("Staking pool is not fed by the reward distributor");
+            StakingError::PoolNotInDistributor
+        })?;
+        pool.accrue(reward_per_weight)?;
+        let amount = pool.accrued_reward_wads.try_floor_u64()?;
+        pool.accrued_reward_wads = pool.accrued_reward_wads.try_sub(Decimal::from(amount))?;
+        Ok(amount)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshSerialize for DistributorPool {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.staking_pool.serialize(writer)?;
+        self.weight.serialize(writer)?;
+        decimal_borsh::serialize(&self.checkpoint, writer)?;
+        decimal_borsh::serialize(&self.accrued_reward_wads, writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshDeserialize for DistributorPool {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        Ok(Self {
+            staking_pool: Pubkey::deserialize(buf)?,
+            weight: u64::deserialize(buf)?,
+            checkpoint: decimal_borsh::deserialize(buf)?,
+            accrued_reward_wads: decimal_borsh::deserialize(buf)?,
+        })
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshSerialize for RewardDistributor {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.version.serialize(writer)?;
+        self.admin_authority.serialize(writer)?;
+        self.reward_token_vault.serialize(writer)?;
+        self.bump_seed.serialize(writer)?;
+        self.time_basis.serialize(writer)?;
+        decimal_borsh::serialize(&self.rate_per_slot, writer)?;
+        decimal_borsh::serialize(&self.reward_per_weight, writer)?;
+        decimal_borsh::serialize(&self.unallocated_reward_wads, writer)?;
+        self.last_update.serialize(writer)?;
+        self.end_time.serialize(writer)?;
+        self.total_weight.serialize(writer)?;
+        self.pools.serialize(writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshDeserialize for RewardDistributor {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        Ok(Self {
+            version: u8::deserialize(buf)?,
+            admin_authority: Pubkey::deserialize(buf)?,
+            reward_token_vault: Pubkey::deserialize(buf)?,
+            bump_seed: u8::deserialize(buf)?,
+            time_basis: TimeBasis::deserialize(buf)?,
+            rate_per_slot: decimal_borsh::deserialize(buf)?,
+            reward_per_weight: decimal_borsh::deserialize(buf)?,
+            unallocated_reward_wads: decimal_borsh::deserialize(buf)?,
+            last_update: Slot::deserialize(buf)?,
+            end_time: Slot::deserialize(buf)?,
+            total_weight: u64::deserialize(buf)?,
+            pools: Vec::deserialize(buf)?,
+        })
+    }
+}
+
+impl Sealed for RewardDistributor {}
+impl IsInitialized for RewardDistributor {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+impl Pack for RewardDistributor {
+    const LEN: usize = 1
+        + PUBKEY_BYTES
+        + PUBKEY_BYTES
+        + 1
+        + 1
+        + Decimal::LEN
+        + Decimal::LEN
+        + Decimal::LEN
+        + 8
+        + 8
+        + 8
+        + 1
+        + DistributorPool::LEN * MAX_DISTRIBUTOR_POOLS
+        + 64;
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let output = array_mut_ref![dst, 0, RewardDistributor::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            admin_authority,
+            reward_token_vault,
+            bump_seed,
+            time_basis,
+            rate_per_slot,
+            reward_per_weight,
+            unallocated_reward_wads,
+            last_update,
+            end_time,
+            total_weight,
+            pools_len,
+            pools_flat,
+            _,
+        ) = mut_array_refs![
+            output,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            1,
+            1,
+            Decimal::LEN,
+            Decimal::LEN,
+            Decimal::LEN,
+            8,
+            8,
+            8,
+            1,
+            DistributorPool::LEN * MAX_DISTRIBUTOR_POOLS,
+            64
+        ];
+        *version = self.version.to_le_bytes();
+        admin_authority.copy_from_slice(self.admin_authority.as_ref());
+        reward_token_vault.copy_from_slice(self.reward_token_vault.as_ref());
+        *bump_seed = self.bump_seed.to_le_bytes();
+        *time_basis = [self.time_basis as u8];
+        self.rate_per_slot.pack_into_slice(rate_per_slot);
+        self.reward_per_weight.pack_into_slice(reward_per_weight);
+        self.unallocated_reward_wads
+            .pack_into_slice(unallocated_reward_wads);
+        *last_update = self.last_update.to_le_bytes();
+        *end_time = self.end_time.to_le_bytes();
+        *total_weight = self.total_weight.to_le_bytes();
+        *pools_len = (self.pools.len() as u8).to_le_bytes();
+
+        pools_flat.fill(0);
+        for (pool, dst) in self
+            .pools
+            .iter()
+            .zip(pools_flat.chunks_exact_mut(DistributorPool::LEN))
+        {
+            let dst = array_mut_ref![dst, 0, DistributorPool::LEN];
+            #[allow(clippy::ptr_offset_with_cast)]
+            let (staking_pool, weight, checkpoint, accrued_reward_wads) =
+                mut_array_refs![dst, PUBKEY_BYTES, 8, Decimal::LEN, Decimal::LEN];
+            staking_pool.copy_from_slice(pool.staking_pool.as_ref());
+            *weight = pool.weight.to_le_bytes();
+            pool.checkpoint.pack_into_slice(checkpoint);
+            pool.accrued_reward_wads
+                .pack_into_slice(accrued_reward_wads);
+        }
+    }
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![src, 0, RewardDistributor::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            admin_authority,
+            reward_token_vault,
+            bump_seed,
+            time_basis,
+            rate_per_slot,
+            reward_per_weight,
+            unallocated_reward_wads,
+            last_update,
+            end_time,
+            total_weight,
+            pools_len,
+            pools_flat,
+            _,
+        ) = array_refs![
+            input,
+            1,
+            PUBKEY_BYTES,
+            PUBKEY_BYTES,
+            1,
+            1,
+            Decimal::LEN,
+            Decimal::LEN,
+            Decimal::LEN,
+            8,
+            8,
+            8,
+            1,
+            DistributorPool::LEN * MAX_DISTRIBUTOR_POOLS,
+            64
+        ];
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("reward distributor version does not match staking program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let pools_len = u8::from_le_bytes(*pools_len) as usize;
+        if pools_len > MAX_DISTRIBUTOR_POOLS {
+            msg!("reward distributor feeds too many staking pools");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut pools = Vec::with_capacity(pools_len);
+        for src in pools_flat
+            .chunks_exact(DistributorPool::LEN)
+            .take(pools_len)
+        {
+            let src = array_ref![src, 0, DistributorPool::LEN];
+            #[allow(clippy::ptr_offset_with_cast)]
+            let (staking_pool, weight, checkpoint, accrued_reward_wads) =
+                array_refs![src, PUBKEY_BYTES, 8, Decimal::LEN, Decimal::LEN];
+            pools.push(DistributorPool {
+                staking_pool: Pubkey::new_from_array(*staking_pool),
+                weight: u64::from_le_bytes(*weight),
+                checkpoint: Decimal::unpack_from_slice(checkpoint)?,
+                accrued_reward_wads: Decimal::unpack_from_slice(accrued_reward_wads)?,
+            });
+        }
+        Ok(Self {
+            version,
+            admin_authority: Pubkey::new_from_array(*admin_authority),
+            reward_token_vault: Pubkey::new_from_array(*reward_token_vault),
+            bump_seed: u8::from_le_bytes(*bump_seed),
+            time_basis: TimeBasis::try_from(u8::from_le_bytes(*time_basis))?,
+            rate_per_slot: Decimal::unpack_from_slice(rate_per_slot)?,
+            reward_per_weight: Decimal::unpack_from_slice(reward_per_weight)?,
+            unallocated_reward_wads: Decimal::unpack_from_slice(unallocated_reward_wads)?,
+            last_update: Slot::from_le_bytes(*last_update),
+            end_time: Slot::from_le_bytes(*end_time),
+            total_weight: u64::from_le_bytes(*total_weight),
+            pools,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::state::PROGRAM_VERSION;
+
+    fn reward_distributor() -> RewardDistributor {
+        RewardDistributor {
+            version: PROGRAM_VERSION,
+            rate_per_slot: Decimal::from(10u64),
+            last_update: 0,
+            end_time: 1_000,
+            ..RewardDistributor::default()
+        }
+    }
+
+    #[test]
+    fn pools_pull_by_weight() {
+        let pool_a = Pubkey::new_unique();
+        let pool_b = Pubkey::new_unique();
+        let mut distributor = reward_distributor();
+        distributor
+            .set_weight(&pool_a, TimeBasis::Slot, 1, 0)
+            .unwrap();
+        distributor
+            .set_weight(&pool_b, TimeBasis::Slot, 3, 0)
+            .unwrap();
+
+        assert_eq!(distributor.pull(&pool_a, TimeBasis::Slot, 100), Ok(250));
+        assert_eq!(distributor.pull(&pool_b, TimeBasis::Slot, 100), Ok(750));
+        assert_eq!(distributor.pull(&pool_b, TimeBasis::Slot, 2_000), Ok(6_750));
+        assert_eq!(distributor.last_update, 1_000);
+        assert_eq!(
+            distributor.pull(&Pubkey::new_unique(), TimeBasis::Slot, 2_000),
+            Err(StakingError::PoolNotInDistributor.into())
+        );
+    }
+
+    #[test]
+    fn emissions_without_weight_are_unallocated() {
+        let pool = Pubkey::new_unique();
+        let mut distributor = reward_distributor();
+        distributor
+            .set_weight(&pool, TimeBasis::Slot, 1, 100)
+            .unwrap();
+
+        assert_eq!(distributor.unallocated_reward_wads, Decimal::from(1_000u64));
+        assert_eq!(distributor.pull(&pool, TimeBasis::Slot, 200), Ok(1_000));
+    }
+
+    #[test]
+    fn update_rejects_earlier_time() {
+        let mut distributor = RewardDistributor {
+            last_update: 100,
+            ..reward_distributor()
+        };
+        assert_eq!(
+            distributor.update(99),
+            Err(StakingError::InvalidCurrentTimeError.into())
+        );
+        assert_eq!(distributor.update(100), Ok(()));
+    }
+
+    #[test]
+    fn removed_pools_return_reward_dust() {
+        let pool_a = Pubkey::new_unique();
+        let pool_b = Pubkey::new_unique();
+        let mut distributor = RewardDistributor {
+            rate_per_slot: Decimal::from(1u64),
+            ..reward_distributor()
+        };
+        distributor
+            .set_weight(&pool_a, TimeBasis::Slot, 1, 0)
+            .unwrap();
+        distributor
+            .set_weight(&pool_b, TimeBasis::Slot, 1, 0)
+            .unwrap();
+        distributor.pull(&pool_a, TimeBasis::Slot, 1).unwrap();
+        distributor
+            .set_weight(&pool_a, TimeBasis::Slot, 0, 1)
+            .unwrap();
+
+        // half a token accrued to the removed pool
+        assert_eq!(distributor.pools.len(), 1);
+        assert_eq!(
+            distributor.unallocated_reward_wads,
+            Decimal::from(1u64).try_div(2).unwrap()
+        );
+        assert_eq!(distributor.total_weight, 1);
+    }
+
+    #[test]
+    fn rejects_pools_of_other_time_basis() {
+        let pool = Pubkey::new_unique();
+        let mut distributor = reward_distributor();
+        assert_eq!(
+            distributor.set_weight(&pool, TimeBasis::UnixTimestamp, 1, 0),
+            Err(StakingError::TimeBasisMismatch.into())
+        );
+        distributor
+            .set_weight(&pool, TimeBasis::Slot, 1, 0)
+            .unwrap();
+        assert_eq!(
+            distributor.pull(&pool, TimeBasis::UnixTimestamp, 100),
+            Err(StakingError::TimeBasisMismatch.into())
+        );
+    }
+}
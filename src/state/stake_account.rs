@@ -1,29 +1,395 @@
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::clock::Slot;
+use solana_program::entrypoint::ProgramResult;
 use solana_program::pubkey::PUBKEY_BYTES;
 
+use crate::error::StakingError;
+#[cfg(feature = "borsh")]
+use crate::serialization::decimal_borsh;
 use crate::solana_program::program_error::ProgramError;
 use crate::solana_program::program_pack::{IsInitialized, Pack, Sealed};
 use crate::solana_program::{msg, pubkey::Pubkey};
-use crate::state::{PROGRAM_VERSION, UNINITIALIZED_VERSION};
-use solana_maths::Decimal;
+use crate::state::{StakingPool, PROGRAM_VERSION, UNINITIALIZED_VERSION};
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_maths::{Decimal, TryAdd, TryDiv, TryMul, TrySub};
 
 #[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StakeAccount {
     /// Version of the struct
     pub version: u8,
     /// rate when last time the state changes
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
     pub start_rate: Decimal,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
     pub owner: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
     pub pool_pubkey: Pubkey,
     pub deposited_amount: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
     pub unclaimed_reward_wads: Decimal,
-    pub reserve_fields1: [u8; 32],
+    // deposit cannot be withdrawn before, 0 for unlocked
+    pub lock_end: u64,
+    // end of the last gauge epoch the deposit voted in, cannot be withdrawn, merged or split
+    // before
+    pub voted_until: Slot,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub reserve_fields1: [u8; 16],
     // since rust on implement traits for array from 0..33 len
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub reserve_fields2: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub reserve_fields3: [u8; 32],
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub reserve_fields4: [u8; 32],
 }
 
+impl StakeAccount {
+    /// Settle rewards accrued since `start_rate` into `unclaimed_reward_wads`.
+    pub fn calculate_reward(&mut self, cumulative_rate: Decimal) -> ProgramResult {
+        if cumulative_rate < self.start_rate {
+            msg!("Cumulative rate is lower than the stake account start rate");
+            return Err(StakingError::InvalidCurrentRateError.into());
+        }
+        let reward = cumulative_rate
+            .try_sub(self.start_rate)?
+            .try_mul(self.deposited_amount)?;
+        self.unclaimed_reward_wads = self.unclaimed_reward_wads.try_add(reward)?;
+        self.start_rate = cumulative_rate;
+        Ok(())
+    }
+
+    /// Withdraw the whole deposit and forfeit all unclaimed rewards, returning the amount
+    /// withdrawn. Only the lock end can stop it: when the pool cannot be updated to
+    /// `current_time` the rewards accrued since the last update are skipped, and the forfeited
+    /// rewards are left owed when they cannot be returned to the pool.
+    pub fn emergency_withdraw(
+        &mut self,
+        staking_pool: &mut StakingPool,
+        current_time: Slot,
+    ) -> Result<u64, ProgramError> {
+        if self.deposited_amount == 0 {
+            msg!("Stake account has nothing to withdraw");
+            return Err(StakingError::StakeWithdrawsZero.into());
+        }
+        self.check_lock_end(current_time)?;
+        let mut refreshed_pool = staking_pool.clone();
+        let mut refreshed = self.clone();
+        let forfeited = refreshed
+            .refresh(&mut refreshed_pool, current_time)
+            .and_then(|_| refreshed_pool.forfeit_reward(refreshed.unclaimed_reward_wads));
+        if forfeited.is_ok() {
+            *staking_pool = refreshed_pool;
+            self.start_rate = refreshed.start_rate;
+        } else {
+            msg!("Skipping reward accrual of the emergency withdrawal");
+        }
+        let amount = self.deposited_amount;
+        staking_pool.pool_size = staking_pool.pool_size.saturating_sub(amount);
+        self.deposited_amount = 0;
+        self.unclaimed_reward_wads = Decimal::zero();
+        Ok(amount)
+    }
+
+    /// Settle this account up to `current_time` and claim its whole unclaimed rewards, returning
+    /// the amount to pay out and the amount forfeited to the early claim penalty. A redistributed
+    /// penalty goes to the other stakers only. The amount is recorded as paid unless the pool
+    /// vests rewards, see `VestingAccount::withdraw_vested`.
+    pub fn claim_reward(
+        &mut self,
+        staking_pool: &mut StakingPool,
+        current_time: Slot,
+    ) -> Result<(u64, u64), ProgramError> {
+        self.refresh(staking_pool, current_time)?;
+        let reward = self.unclaimed_reward_wads.try_floor_u64()?;
+        let forfeited = staking_pool.early_claim_penalty(reward, current_time)?;
+        self.unclaimed_reward_wads = self.unclaimed_reward_wads.try_sub(Decimal::from(reward))?;
+        staking_pool.forfeit_claim_penalty(forfeited, self.deposited_amount)?;
+        self.start_rate = staking_pool.cumulative_rate;
+        let amount = reward
+            .checked_sub(forfeited)
+            .ok_or(StakingError::MathOverflow)?;
+        if !staking_pool.vests_rewards() {
+            staking_pool.pay_reward(amount)?;
+        }
+        Ok((amount, forfeited))
+    }
+
+    /// Unclaimed rewards at `current_time` and the part a claim would forfeit, the whole rewards
+    /// when the pool rejects claims at `current_time`. Changes neither account, so the share of
+    /// a reward distributor not yet pulled into the pool is not included.
+    pub fn pending_reward(
+        &self,
+        staking_pool: &StakingPool,
+        current_time: Slot,
+    ) -> Result<(u64, u64), ProgramError> {
+        let mut stake_account = self.clone();
+        let mut staking_pool = staking_pool.clone();
+        stake_account.refresh(&mut staking_pool, current_time)?;
+        let reward = stake_account.unclaimed_reward_wads.try_floor_u64()?;
+        let penalty = if current_time < staking_pool.earliest_reward_claim_time
+            && staking_pool.early_claim_penalty_bps == 0
+        {
+            reward
+        } else {
+            staking_pool.early_claim_penalty(reward, current_time)?
+        };
+        Ok((reward, penalty))
+    }
+
+    /// Settle this account up to `current_time` and turn its whole unclaimed rewards, less the
+    /// early claim penalty, into deposit, returning the amount compounded and recorded as paid.
+    /// Only valid in custodial pools whose reward mint is the staked asset and that do not vest
+    /// rewards, the reward tokens move to the stake vault.
+    pub fn compound_reward(
+        &mut self,
+        staking_pool: &mut StakingPool,
+        current_time: Slot,
+    ) -> Result<u64, ProgramError> {
+        if !staking_pool.custodial {
+            msg!("Rewards can only be compounded in a custodial staking pool");
+            return Err(StakingError::InvalidStakingPool.into());
+        }
+        if staking_pool.vests_rewards() {
+            msg!("Rewards cannot be compounded in a staking pool that vests them");
+            return Err(StakingError::InvalidStakingPool.into());
+        }
+        self.refresh(staking_pool, current_time)?;
+        let reward = self.unclaimed_reward_wads.try_floor_u64()?;
+        let penalty = staking_pool.early_claim_penalty(reward, current_time)?;
+        let amount = reward
+            .checked_sub(penalty)
+            .ok_or(StakingError::MathOverflow)?;
+        if amount == 0 {
+            msg!("Stake account has no reward to compound");
+            return Err(StakingError::StakeDepositsZero.into());
+        }
+        staking_pool.check_deposit_limits(self.deposited_amount, amount)?;
+        self.unclaimed_reward_wads = self.unclaimed_reward_wads.try_sub(Decimal::from(reward))?;
+        staking_pool.forfeit_claim_penalty(penalty, self.deposited_amount)?;
+        self.start_rate = staking_pool.cumulative_rate;
+        staking_pool.pay_reward(amount)?;
+        self.deposited_amount = self
+            .deposited_amount
+            .checked_add(amount)
+            .ok_or(StakingError::MathOverflow)?;
+        staking_pool.pool_size = staking_pool
+            .pool_size
+            .checked_add(amount)
+            .ok_or(StakingError::MathOverflow)?;
+        Ok(amount)
+    }
+
+    /// Settle this account and `source`, another stake account of the same owner in the same
+    /// pool, up to `current_time` and move the deposit and unclaimed rewards of `source` into
+    /// this account, leaving `source` empty.
+    pub fn merge(
+        &mut self,
+        source: &mut StakeAccount,
+        staking_pool: &mut StakingPool,
+        current_time: Slot,
+    ) -> ProgramResult {
+        if source.owner != self.owner {
+            msg!("Stake accounts to merge have different owners");
+            return Err(StakingError::InvalidStakeAccount.into());
+        }
+        if source.pool_pubkey != self.pool_pubkey {
+            msg!("Stake accounts to merge are in different staking pools");
+            return Err(StakingError::InvalidStakeAccount.into());
+        }
+        source.check_not_voted(current_time)?;
+        self.refresh(staking_pool, current_time)?;
+        source.calculate_reward(staking_pool.cumulative_rate)?;
+
+        let deposited_amount = self
+            .deposited_amount
+            .checked_add(source.deposited_amount)
+            .ok_or(StakingError::MathOverflow)?;
+        if staking_pool.max_deposit_per_account != 0
+            && deposited_amount > staking_pool.max_deposit_per_account
+        {
+            msg!("Merged deposit would exceed the maximum deposit of a stake account");
+            return Err(StakingError::AccountDepositLimitExceeded.into());
+        }
+        self.deposited_amount = deposited_amount;
+        self.unclaimed_reward_wads = self
+            .unclaimed_reward_wads
+            .try_add(source.unclaimed_reward_wads)?;
+        self.lock_end = self.lock_end.max(source.lock_end);
+        source.deposited_amount = 0;
+        source.unclaimed_reward_wads = Decimal::zero();
+        Ok(())
+    }
+
+    /// Settle this account up to `current_time` and move `amount` of its deposit into
+    /// `new_account`, an initialized stake account of the same pool, with `reward_amount` of its
+    /// unclaimed rewards or, when `None`, the share of `amount` in the deposit.
+    pub fn split(
+        &mut self,
+        new_account: &mut StakeAccount,
+        amount: u64,
+        reward_amount: Option<u64>,
+        staking_pool: &mut StakingPool,
+        current_time: Slot,
+    ) -> ProgramResult {
+        if new_account.pool_pubkey != self.pool_pubkey {
+            msg!("Stake accounts to split are in different staking pools");
+            return Err(StakingError::InvalidStakeAccount.into());
+        }
+        if amount == 0 {
+            msg!("Split amount must be non zero");
+            return Err(StakingError::StakeWithdrawsZero.into());
+        }
+        if amount > self.deposited_amount {
+            msg!("Split amount is larger than the deposit");
+            return Err(StakingError::InvalidWithdrawAmountError.into());
+        }
+        self.check_not_voted(current_time)?;
+        self.refresh(staking_pool, current_time)?;
+        new_account.calculate_reward(staking_pool.cumulative_rate)?;
+
+        let reward_wads = match reward_amount {
+            Some(reward_amount) => Decimal::from(reward_amount),
+            None => self
+                .unclaimed_reward_wads
+                .try_mul(amount)?
+                .try_div(self.deposited_amount)?,
+        };
+        if reward_wads > self.unclaimed_reward_wads {
+            msg!("Split reward amount is larger than the unclaimed rewards");
+            return Err(StakingError::InvalidArgumentError.into());
+        }
+        self.deposited_amount -= amount;
+        self.unclaimed_reward_wads = self.unclaimed_reward_wads.try_sub(reward_wads)?;
+        new_account.deposited_amount = new_account
+            .deposited_amount
+            .checked_add(amount)
+            .ok_or(StakingError::MathOverflow)?;
+        new_account.unclaimed_reward_wads =
+            new_account.unclaimed_reward_wads.try_add(reward_wads)?;
+        new_account.lock_end = new_account.lock_end.max(self.lock_end);
+        Ok(())
+    }
+
+    /// Check that the deposit can be withdrawn at `current_time`.
+    pub fn check_unlocked(&self, current_time: Slot) -> ProgramResult {
+        self.check_lock_end(current_time)?;
+        self.check_not_voted(current_time)
+    }
+
+    fn check_lock_end(&self, current_time: Slot) -> ProgramResult {
+        if current_time < self.lock_end {
+            msg!("Stake account is locked until {}", self.lock_end);
+            return Err(StakingError::StakeLocked.into());
+        }
+        Ok(())
+    }
+
+    /// Check that the deposit is not counted in a gauge epoch still running at `current_time`,
+    /// so it cannot be moved to vote again.
+    pub fn check_not_voted(&self, current_time: Slot) -> ProgramResult {
+        if current_time < self.voted_until {
+            msg!(
+                "Stake account voted in a gauge epoch ending at {}",
+                self.voted_until
+            );
+            return Err(StakingError::StakeLocked.into());
+        }
+        Ok(())
+    }
+
+    /// Lock the deposit until `lock_end`, no earlier than the current lock end and at most the
+    /// maximum lock duration of the pool after `current_time`.
+    pub fn extend_lock(
+        &mut self,
+        lock_end: u64,
+        staking_pool: &StakingPool,
+        current_time: Slot,
+    ) -> ProgramResult {
+        let max_lock_end = current_time
+            .checked_add(staking_pool.max_lock_duration)
+            .ok_or(StakingError::MathOverflow)?;
+        if staking_pool.max_lock_duration == 0 || lock_end > max_lock_end {
+            msg!("Lock end is beyond the maximum lock duration of the staking pool");
+            return Err(StakingError::InvalidLockEnd.into());
+        }
+        if lock_end < self.lock_end || lock_end <= current_time {
+            msg!("Lock end must extend the current lock");
+            return Err(StakingError::InvalidLockEnd.into());
+        }
+        self.lock_end = lock_end;
+        Ok(())
+    }
+
+    /// Voting power of the deposit at `time`, no earlier than `current_time`, the deposit scaled
+    /// by the remaining lock over the maximum lock duration of the pool, or the whole deposit
+    /// when the pool has no locks. Past voting power is not kept, so earlier times are rejected.
+    pub fn voting_power(
+        &self,
+        staking_pool: &StakingPool,
+        time: Slot,
+        current_time: Slot,
+    ) -> Result<u64, ProgramError> {
+        if time < current_time {
+            msg!("Voting power is only known from the current time on");
+            return Err(StakingError::InvalidArgumentError.into());
+        }
+        let max_lock_duration = staking_pool.max_lock_duration;
+        if max_lock_duration == 0 {
+            return Ok(self.deposited_amount);
+        }
+        let remaining_lock = self.lock_end.saturating_sub(time).min(max_lock_duration);
+        let voting_power = u128::from(self.deposited_amount) * u128::from(remaining_lock)
+            / u128::from(max_lock_duration);
+        Ok(voting_power as u64)
+    }
+
+    /// Update the staking pool to `current_time` and settle this account against it,
+    /// producing the same result as the `RefreshStakeAccount` instruction.
+    pub fn refresh(&mut self, staking_pool: &mut StakingPool, current_time: Slot) -> ProgramResult {
+        staking_pool.update(current_time)?;
+        self.calculate_reward(staking_pool.cumulative_rate)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshSerialize for StakeAccount {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.version.serialize(writer)?;
+        decimal_borsh::serialize(&self.start_rate, writer)?;
+        self.owner.serialize(writer)?;
+        self.pool_pubkey.serialize(writer)?;
+        self.deposited_amount.serialize(writer)?;
+        decimal_borsh::serialize(&self.unclaimed_reward_wads, writer)?;
+        self.lock_end.serialize(writer)?;
+        self.voted_until.serialize(writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshDeserialize for StakeAccount {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        Ok(Self {
+            version: u8::deserialize(buf)?,
+            start_rate: decimal_borsh::deserialize(buf)?,
+            owner: Pubkey::deserialize(buf)?,
+            pool_pubkey: Pubkey::deserialize(buf)?,
+            deposited_amount: u64::deserialize(buf)?,
+            unclaimed_reward_wads: decimal_borsh::deserialize(buf)?,
+            lock_end: u64::deserialize(buf)?,
+            voted_until: Slot::deserialize(buf)?,
+            ..Self::default()
+        })
+    }
+}
+
 impl Sealed for StakeAccount {}
 impl IsInitialized for StakeAccount {
     fn is_initialized(&self) -> bool {
@@ -31,11 +397,22 @@ impl IsInitialized for StakeAccount {
     }
 }
 impl Pack for StakeAccount {
-    const LEN: usize = 1 + Decimal::LEN + PUBKEY_BYTES + PUBKEY_BYTES + 8 + Decimal::LEN + 128;
+    const LEN: usize =
+        1 + Decimal::LEN + PUBKEY_BYTES + PUBKEY_BYTES + 8 + Decimal::LEN + 8 + 8 + 112;
     fn pack_into_slice(&self, dst: &mut [u8]) {
         let output = array_mut_ref![dst, 0, StakeAccount::LEN];
         #[allow(clippy::ptr_offset_with_cast)]
-        let (version, start_rate, owner, pool_pubkey, deposited_value, unclaimed_reward_wads, _) = mut_array_refs![
+        let (
+            version,
+            start_rate,
+            owner,
+            pool_pubkey,
+            deposited_value,
+            unclaimed_reward_wads,
+            lock_end,
+            voted_until,
+            _,
+        ) = mut_array_refs![
             output,
             1,
             Decimal::LEN,
@@ -43,7 +420,9 @@ impl Pack for StakeAccount {
             PUBKEY_BYTES,
             8,
             Decimal::LEN,
-            128
+            8,
+            8,
+            112
         ];
         *version = self.version.to_le_bytes();
         self.start_rate.pack_into_slice(start_rate);
@@ -52,11 +431,23 @@ impl Pack for StakeAccount {
         *deposited_value = self.deposited_amount.to_le_bytes();
         self.unclaimed_reward_wads
             .pack_into_slice(unclaimed_reward_wads);
+        *lock_end = self.lock_end.to_le_bytes();
+        *voted_until = self.voted_until.to_le_bytes();
     }
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
         let input = array_ref![src, 0, StakeAccount::LEN];
         #[allow(clippy::ptr_offset_with_cast)]
-        let (version, start_rate, owner, pool_pubkey, deposited_value, unclaimed_reward_wads, _) = array_refs![
+        let (
+            version,
+            start_rate,
+            owner,
+            pool_pubkey,
+            deposited_value,
+            unclaimed_reward_wads,
+            lock_end,
+            voted_until,
+            _,
+        ) = array_refs![
             input,
             1,
             Decimal::LEN,
@@ -64,7 +455,9 @@ impl Pack for StakeAccount {
             PUBKEY_BYTES,
             8,
             Decimal::LEN,
-            128
+            8,
+            8,
+            112
         ];
         let version = u8::from_le_bytes(*version);
         if version > PROGRAM_VERSION {
@@ -76,6 +469,8 @@ impl Pack for StakeAccount {
         let pool_pubkey = Pubkey::new_from_array(*pool_pubkey);
         let deposited_value = u64::from_le_bytes(*deposited_value);
         let unclaimed_reward_wads = Decimal::unpack_from_slice(unclaimed_reward_wads)?;
+        let lock_end = u64::from_le_bytes(*lock_end);
+        let voted_until = Slot::from_le_bytes(*voted_until);
         let reserve_field = [0; 32];
         Ok(Self {
             version,
@@ -84,10 +479,482 @@ impl Pack for StakeAccount {
             pool_pubkey,
             deposited_amount: deposited_value,
             unclaimed_reward_wads,
-            reserve_fields1: reserve_field,
+            lock_end,
+            voted_until,
+            reserve_fields1: [0; 16],
             reserve_fields2: reserve_field,
             reserve_fields3: reserve_field,
             reserve_fields4: reserve_field,
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::fixtures::{owned_stake_account, stake_account, staking_pool};
+    use crate::state::EarlyClaimPenaltyMode;
+
+    #[test]
+    fn refresh_settles_pending_reward() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(25);
+        account.refresh(&mut pool, 40).unwrap();
+
+        // 400 emitted over 40 slots, a quarter of it to this account
+        assert_eq!(account.unclaimed_reward_wads, Decimal::from(100u64));
+        assert_eq!(account.start_rate, pool.cumulative_rate);
+        assert_eq!(pool.last_update, 40);
+    }
+
+    #[test]
+    fn refresh_matches_settlement_on_deposit() {
+        let mut refreshed_pool = staking_pool(100);
+        let mut refreshed = stake_account(25);
+        for &time in &[10, 35, 40] {
+            refreshed.refresh(&mut refreshed_pool, time).unwrap();
+        }
+
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(25);
+        pool.update(40).unwrap();
+        account.calculate_reward(pool.cumulative_rate).unwrap();
+
+        assert_eq!(refreshed, account);
+        assert_eq!(refreshed_pool, pool);
+    }
+
+    #[test]
+    fn refresh_stops_accruing_at_end_time() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(100);
+        account.refresh(&mut pool, 5_000).unwrap();
+
+        assert_eq!(account.unclaimed_reward_wads, Decimal::from(10_000u64));
+        assert_eq!(pool.last_update, 1_000);
+    }
+
+    #[test]
+    fn refresh_rejects_earlier_time() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(25);
+        account.refresh(&mut pool, 40).unwrap();
+
+        assert_eq!(
+            account.refresh(&mut pool, 39),
+            Err(StakingError::InvalidCurrentTimeError.into())
+        );
+    }
+
+    #[test]
+    fn emergency_withdraw_forfeits_rewards_to_pool() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(25);
+        account.refresh(&mut pool, 10).unwrap();
+
+        assert_eq!(account.emergency_withdraw(&mut pool, 40), Ok(25));
+        assert_eq!(account.deposited_amount, 0);
+        assert_eq!(account.unclaimed_reward_wads, Decimal::zero());
+        assert_eq!(pool.pool_size, 75);
+        // a quarter of the 400 emitted so far
+        assert_eq!(pool.unallocated_reward_wads, Decimal::from(100u64));
+        assert_eq!(pool.last_update, 40);
+    }
+
+    #[test]
+    fn emergency_withdraw_skips_accrual_it_cannot_settle() {
+        let mut pool = staking_pool(100);
+        pool.update(50).unwrap();
+        let mut account = stake_account(25);
+        account.voted_until = 100;
+
+        // the clock went back before the last update of the pool
+        assert_eq!(account.emergency_withdraw(&mut pool, 40), Ok(25));
+        assert_eq!(account.deposited_amount, 0);
+        assert_eq!(pool.pool_size, 75);
+        assert_eq!(pool.last_update, 50);
+        assert_eq!(pool.unallocated_reward_wads, Decimal::zero());
+    }
+
+    #[test]
+    fn emergency_withdraw_keeps_lock_end() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(25);
+        account.lock_end = 50;
+
+        assert_eq!(
+            account.emergency_withdraw(&mut pool, 40),
+            Err(StakingError::StakeLocked.into())
+        );
+        assert_eq!(account.emergency_withdraw(&mut pool, 50), Ok(25));
+    }
+
+    #[test]
+    fn emergency_withdraw_rejects_empty_account() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(0);
+
+        assert_eq!(
+            account.emergency_withdraw(&mut pool, 40),
+            Err(StakingError::StakeWithdrawsZero.into())
+        );
+        assert_eq!(pool, staking_pool(100));
+    }
+
+    #[test]
+    fn claim_reward_records_liquid_claims_as_paid() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(25);
+
+        assert_eq!(account.claim_reward(&mut pool, 40), Ok((100, 0)));
+        assert_eq!(pool.owed_reward_wads, Decimal::from(300u64));
+
+        let mut pool = StakingPool {
+            vesting_duration: 100,
+            ..staking_pool(100)
+        };
+        let mut account = stake_account(25);
+        assert_eq!(account.claim_reward(&mut pool, 40), Ok((100, 0)));
+        assert_eq!(pool.owed_reward_wads, Decimal::from(400u64));
+    }
+
+    #[test]
+    fn merge_moves_deposit_and_rewards() {
+        let owner = Pubkey::new_unique();
+        let mut pool = staking_pool(100);
+        let mut account = owned_stake_account(owner, 25);
+        let mut source = owned_stake_account(owner, 75);
+        source.lock_end = 500;
+        account.merge(&mut source, &mut pool, 40).unwrap();
+
+        assert_eq!(account.deposited_amount, 100);
+        assert_eq!(account.unclaimed_reward_wads, Decimal::from(400u64));
+        assert_eq!(account.lock_end, 500);
+        assert_eq!(source.deposited_amount, 0);
+        assert_eq!(source.unclaimed_reward_wads, Decimal::zero());
+        assert_eq!(source.start_rate, pool.cumulative_rate);
+        assert_eq!(pool.pool_size, 100);
+    }
+
+    #[test]
+    fn merge_rejects_other_owner_or_limit() {
+        let owner = Pubkey::new_unique();
+        let mut pool = StakingPool {
+            max_deposit_per_account: 90,
+            ..staking_pool(100)
+        };
+        let mut account = owned_stake_account(owner, 25);
+        let mut source = owned_stake_account(Pubkey::new_unique(), 75);
+        assert_eq!(
+            account.merge(&mut source, &mut pool, 40),
+            Err(StakingError::InvalidStakeAccount.into())
+        );
+
+        let mut source = owned_stake_account(owner, 75);
+        assert_eq!(
+            account.merge(&mut source, &mut pool, 40),
+            Err(StakingError::AccountDepositLimitExceeded.into())
+        );
+    }
+
+    #[test]
+    fn split_moves_share_of_rewards() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(100);
+        account.lock_end = 500;
+        let mut new_account = stake_account(0);
+        account
+            .split(&mut new_account, 25, None, &mut pool, 40)
+            .unwrap();
+
+        assert_eq!(account.deposited_amount, 75);
+        assert_eq!(account.unclaimed_reward_wads, Decimal::from(300u64));
+        assert_eq!(new_account.deposited_amount, 25);
+        assert_eq!(new_account.unclaimed_reward_wads, Decimal::from(100u64));
+        assert_eq!(new_account.start_rate, pool.cumulative_rate);
+        assert_eq!(new_account.lock_end, 500);
+        assert_eq!(pool.pool_size, 100);
+    }
+
+    #[test]
+    fn split_moves_given_rewards() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(100);
+        let mut new_account = stake_account(0);
+        account
+            .split(&mut new_account, 25, Some(400), &mut pool, 40)
+            .unwrap();
+
+        assert_eq!(account.unclaimed_reward_wads, Decimal::zero());
+        assert_eq!(new_account.unclaimed_reward_wads, Decimal::from(400u64));
+    }
+
+    #[test]
+    fn split_rejects_invalid_amounts() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(100);
+        let mut new_account = stake_account(0);
+        let mut split = |amount, reward_amount| {
+            account.split(&mut new_account, amount, reward_amount, &mut pool, 40)
+        };
+
+        assert_eq!(split(0, None), Err(StakingError::StakeWithdrawsZero.into()));
+        assert_eq!(
+            split(101, None),
+            Err(StakingError::InvalidWithdrawAmountError.into())
+        );
+        assert_eq!(
+            split(50, Some(401)),
+            Err(StakingError::InvalidArgumentError.into())
+        );
+
+        let mut other_pool_account = StakeAccount {
+            pool_pubkey: Pubkey::new_unique(),
+            ..stake_account(0)
+        };
+        assert_eq!(
+            account.split(&mut other_pool_account, 50, None, &mut pool, 40),
+            Err(StakingError::InvalidStakeAccount.into())
+        );
+    }
+
+    #[test]
+    fn compound_reward_moves_reward_into_deposit() {
+        let mut pool = StakingPool {
+            custodial: true,
+            ..staking_pool(100)
+        };
+        let mut account = stake_account(25);
+
+        assert_eq!(account.compound_reward(&mut pool, 40), Ok(100));
+        assert_eq!(account.deposited_amount, 125);
+        assert_eq!(account.unclaimed_reward_wads, Decimal::zero());
+        assert_eq!(pool.pool_size, 200);
+        // paid out of the reward token pool into the stake vault
+        assert_eq!(pool.owed_reward_wads, Decimal::from(300u64));
+
+        // no reward left to compound
+        assert_eq!(
+            account.compound_reward(&mut pool, 40),
+            Err(StakingError::StakeDepositsZero.into())
+        );
+    }
+
+    #[test]
+    fn compound_reward_rejects_non_custodial_pool() {
+        let mut pool = staking_pool(100);
+        let mut account = stake_account(25);
+
+        assert_eq!(
+            account.compound_reward(&mut pool, 40),
+            Err(StakingError::InvalidStakingPool.into())
+        );
+        assert_eq!(account, stake_account(25));
+    }
+
+    #[test]
+    fn compound_reward_rejects_vesting_pool() {
+        let mut pool = StakingPool {
+            custodial: true,
+            vesting_duration: 100,
+            ..staking_pool(100)
+        };
+        let mut account = stake_account(25);
+
+        assert_eq!(
+            account.compound_reward(&mut pool, 40),
+            Err(StakingError::InvalidStakingPool.into())
+        );
+        assert_eq!(account, stake_account(25));
+    }
+
+    #[test]
+    fn compound_reward_respects_deposit_limits() {
+        let mut pool = StakingPool {
+            custodial: true,
+            max_deposit_per_account: 100,
+            ..staking_pool(100)
+        };
+        let mut account = stake_account(25);
+
+        assert_eq!(
+            account.compound_reward(&mut pool, 40),
+            Err(StakingError::AccountDepositLimitExceeded.into())
+        );
+    }
+
+    #[test]
+    fn claim_reward_forfeits_early_claim_penalty() {
+        let mut pool = StakingPool {
+            start_time: 1,
+            earliest_reward_claim_time: 81,
+            early_claim_penalty_bps: 5_000,
+            early_claim_penalty_mode: EarlyClaimPenaltyMode::ReturnToPool,
+            ..staking_pool(100)
+        };
+        let mut account = stake_account(25);
+
+        // halfway through the window, a quarter of the rewards are forfeited
+        assert_eq!(account.claim_reward(&mut pool, 41), Ok((75, 25)));
+        assert_eq!(account.unclaimed_reward_wads, Decimal::zero());
+        assert_eq!(pool.unallocated_reward_wads, Decimal::from(25u64));
+        assert_eq!(account.pending_reward(&pool, 81), Ok((100, 0)));
+    }
+
+    #[test]
+    fn distributed_reward_counts_once_pulled() {
+        let mut pool = StakingPool {
+            rate_per_slot: Decimal::zero(),
+            ..staking_pool(100)
+        };
+        let mut account = stake_account(25);
+        let mut pulled_pool = pool.clone();
+        let mut pulled_account = account.clone();
+
+        // claiming without the pull misses the share left in the distributor
+        assert_eq!(account.claim_reward(&mut pool, 40), Ok((0, 0)));
+
+        pulled_pool.receive_distributed_reward(400, 40).unwrap();
+        assert_eq!(
+            pulled_account.claim_reward(&mut pulled_pool, 40),
+            Ok((100, 0))
+        );
+    }
+
+    #[test]
+    fn claim_reward_redistributes_penalty_to_other_stakers() {
+        let mut pool = StakingPool {
+            start_time: 1,
+            earliest_reward_claim_time: 81,
+            early_claim_penalty_bps: 5_000,
+            early_claim_penalty_mode: EarlyClaimPenaltyMode::Redistribute,
+            ..staking_pool(125)
+        };
+        let mut account = stake_account(25);
+        let mut other = stake_account(100);
+
+        assert_eq!(account.claim_reward(&mut pool, 41), Ok((60, 20)));
+        // none of the penalty comes back to the claimer
+        assert_eq!(account.start_rate, pool.cumulative_rate);
+        assert_eq!(account.pending_reward(&pool, 41), Ok((0, 0)));
+        assert_eq!(pool.unallocated_reward_wads, Decimal::zero());
+
+        // the other stakers share all of it on top of their own rewards
+        other.refresh(&mut pool, 41).unwrap();
+        assert_eq!(other.unclaimed_reward_wads, Decimal::from(340u64));
+    }
+
+    #[test]
+    fn pending_reward_is_all_penalty_when_early_claims_are_rejected() {
+        let pool = StakingPool {
+            earliest_reward_claim_time: 500,
+            ..staking_pool(100)
+        };
+        let mut account = stake_account(25);
+
+        assert_eq!(account.pending_reward(&pool, 40), Ok((100, 100)));
+        assert_eq!(
+            account.claim_reward(&mut pool.clone(), 40),
+            Err(StakingError::InvalidCurrentTimeError.into())
+        );
+    }
+
+    #[test]
+    fn gauge_vote_locks_withdraw_merge_and_split() {
+        let owner = Pubkey::new_unique();
+        let mut pool = staking_pool(100);
+        let mut account = owned_stake_account(owner, 50);
+        account.voted_until = 60;
+        let mut other = owned_stake_account(owner, 50);
+
+        assert_eq!(
+            other.merge(&mut account, &mut pool, 40),
+            Err(StakingError::StakeLocked.into())
+        );
+        assert_eq!(
+            account.split(&mut other, 10, None, &mut pool, 40),
+            Err(StakingError::StakeLocked.into())
+        );
+        assert_eq!(
+            account.check_unlocked(40),
+            Err(StakingError::StakeLocked.into())
+        );
+        // a voted destination can still receive a deposit that has not voted
+        account.merge(&mut other, &mut pool, 40).unwrap();
+        assert_eq!(account.deposited_amount, 100);
+
+        account.split(&mut other, 10, None, &mut pool, 60).unwrap();
+        assert_eq!(account.emergency_withdraw(&mut pool, 60), Ok(90));
+    }
+
+    #[test]
+    fn pack_round_trip() {
+        let account = StakeAccount {
+            owner: Pubkey::new_unique(),
+            pool_pubkey: Pubkey::new_unique(),
+            unclaimed_reward_wads: Decimal::from(3u64),
+            lock_end: 500,
+            voted_until: 600,
+            ..stake_account(25)
+        };
+        let mut data = vec![0; StakeAccount::LEN];
+        StakeAccount::pack(account.clone(), &mut data).unwrap();
+        assert_eq!(StakeAccount::unpack(&data), Ok(account));
+    }
+
+    fn lockable_pool() -> StakingPool {
+        StakingPool {
+            max_lock_duration: 1_000,
+            ..staking_pool(100)
+        }
+    }
+
+    #[test]
+    fn extend_lock_within_max_lock_duration() {
+        let pool = lockable_pool();
+        let mut account = stake_account(100);
+        account.extend_lock(600, &pool, 100).unwrap();
+        assert_eq!(account.lock_end, 600);
+        assert_eq!(
+            account.check_unlocked(599),
+            Err(StakingError::StakeLocked.into())
+        );
+        assert_eq!(account.check_unlocked(600), Ok(()));
+
+        assert_eq!(
+            account.extend_lock(1_101, &pool, 100),
+            Err(StakingError::InvalidLockEnd.into())
+        );
+        assert_eq!(
+            account.extend_lock(500, &pool, 100),
+            Err(StakingError::InvalidLockEnd.into())
+        );
+        assert_eq!(
+            account.extend_lock(700, &staking_pool(100), 100),
+            Err(StakingError::InvalidLockEnd.into())
+        );
+        account.extend_lock(1_100, &pool, 100).unwrap();
+        assert_eq!(account.lock_end, 1_100);
+    }
+
+    #[test]
+    fn voting_power_scales_with_remaining_lock() {
+        let pool = lockable_pool();
+        let mut account = stake_account(100);
+        account.lock_end = 600;
+
+        assert_eq!(account.voting_power(&pool, 100, 100), Ok(50));
+        assert_eq!(account.voting_power(&pool, 350, 100), Ok(25));
+        assert_eq!(account.voting_power(&pool, 600, 100), Ok(0));
+        assert_eq!(
+            account.voting_power(&pool, 99, 100),
+            Err(StakingError::InvalidArgumentError.into())
+        );
+
+        // the remaining lock counts up to the maximum lock duration
+        account.lock_end = 5_000;
+        assert_eq!(account.voting_power(&pool, 100, 100), Ok(100));
+        // the whole deposit votes in pools without locks
+        assert_eq!(account.voting_power(&staking_pool(100), 100, 100), Ok(100));
+    }
+}
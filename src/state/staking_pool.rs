@@ -1,33 +1,584 @@
+use std::cmp::{max, min};
+use std::convert::TryFrom;
+
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
-use solana_program::clock::Slot;
+use solana_program::clock::{Clock, Slot};
+use solana_program::entrypoint::ProgramResult;
 use solana_program::program_error::ProgramError;
 use solana_program::pubkey::PUBKEY_BYTES;
 use solana_program::{msg, pubkey::Pubkey};
 
+use crate::error::StakingError;
+#[cfg(feature = "borsh")]
+use crate::serialization::decimal_borsh;
 use crate::solana_program::program_pack::{IsInitialized, Pack, Sealed};
-use crate::state::{PROGRAM_VERSION, UNINITIALIZED_VERSION};
-use solana_maths::Decimal;
+use crate::state::{unpack_bool, AllowlistEntry, PROGRAM_VERSION, UNINITIALIZED_VERSION};
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_maths::{Decimal, TryAdd, TryDiv, TryMul, TrySub};
+
+/// Basis points in a whole.
+pub const MAX_BPS: u16 = 10_000;
+
+/// Seed prefix of stake vault addresses, followed by the staking pool.
+pub const STAKE_VAULT_SEED: &[u8] = b"stake_vault";
+
+/// Address of the token account holding the deposits of a custodial `staking_pool`, with its
+/// bump seed. The vault is owned by the staking program derived authority of the pool.
+pub fn find_stake_vault_address(program_id: &Pubkey, staking_pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[STAKE_VAULT_SEED, staking_pool.as_ref()], program_id)
+}
+
+/// Unit of every time in a staking pool: `last_update`, `end_time`, `duration`,
+/// `earliest_reward_claim_time` and `start_time`, and of the period of `rate_per_slot`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub enum TimeBasis {
+    /// `Clock::slot`
+    Slot = 0,
+    /// `Clock::unix_timestamp`, in seconds
+    UnixTimestamp = 1,
+}
+
+impl Default for TimeBasis {
+    fn default() -> Self {
+        TimeBasis::Slot
+    }
+}
+
+impl TimeBasis {
+    /// Current time of the clock in this basis.
+    pub fn current_time(&self, clock: &Clock) -> Result<u64, ProgramError> {
+        match self {
+            TimeBasis::Slot => Ok(clock.slot),
+            TimeBasis::UnixTimestamp => u64::try_from(clock.unix_timestamp).map_err(|_| {
+                msg!("Unix timestamp is negative");
+                StakingError::InvalidCurrentTimeError.into()
+            }),
+        }
+    }
+}
+
+impl TryFrom<u8> for TimeBasis {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(TimeBasis::Slot),
+            1 => Ok(TimeBasis::UnixTimestamp),
+            _ => {
+                msg!("Time basis cannot be unpacked");
+                Err(ProgramError::InvalidAccountData)
+            }
+        }
+    }
+}
+
+/// Destination of the rewards forfeited by early claims.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub enum EarlyClaimPenaltyMode {
+    /// Shared among the current stakers
+    Redistribute = 0,
+    /// Added to `unallocated_reward_wads`
+    ReturnToPool = 1,
+}
+
+impl Default for EarlyClaimPenaltyMode {
+    fn default() -> Self {
+        EarlyClaimPenaltyMode::Redistribute
+    }
+}
+
+impl TryFrom<u8> for EarlyClaimPenaltyMode {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(EarlyClaimPenaltyMode::Redistribute),
+            1 => Ok(EarlyClaimPenaltyMode::ReturnToPool),
+            _ => {
+                msg!("Early claim penalty mode cannot be unpacked");
+                Err(ProgramError::InvalidAccountData)
+            }
+        }
+    }
+}
 
 #[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StakingPool {
     /// Version of the struct
     pub version: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
     pub owner_authority: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
     pub admin_authority: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
     pub reward_token_pool: Pubkey,
     pub last_update: Slot,
     // last time the state changes
     pub end_time: Slot,
     pub earliest_reward_claim_time: Slot,
     pub duration: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
     pub rate_per_slot: Decimal,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
     pub cumulative_rate: Decimal,
     pub pool_size: u64,
     pub bump_seed_staking_program: u8,
-    pub reserve_fields1: [u8; 32], // since rust on implement traits for array from 0..33 len
-    pub reserve_fields2: [u8; 32],
-    pub reserve_fields3: [u8; 32],
-    pub reserve_fields4: [u8; 32],
+    /// rewards emitted while the pool was empty or forfeited by stakers
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
+    pub unallocated_reward_wads: Decimal,
+    // reward accrual starts here
+    pub start_time: Slot,
+    pub deposit_before_start: bool,
+    // unit of every time above, see `current_time`
+    pub time_basis: TimeBasis,
+    // 0 for no limit
+    pub max_pool_size: u64,
+    // 0 for no limit
+    pub max_deposit_per_account: u64,
+    // stake accounts need a gatekeeper signature or an allowlist entry
+    pub allowlist_enabled: bool,
+    // default pubkey for no gatekeeper
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub gatekeeper: Pubkey,
+    // deposits are held in the stake vault, see `find_stake_vault_address`
+    pub custodial: bool,
+    // share of rewards forfeited by claims at `reward_start_time`, decreasing linearly to none at
+    // `earliest_reward_claim_time`; 0 to reject early claims
+    pub early_claim_penalty_bps: u16,
+    pub early_claim_penalty_mode: EarlyClaimPenaltyMode,
+    // claimed rewards vest linearly over `vesting_duration`, none before `vesting_cliff`;
+    // 0 duration for liquid claims
+    pub vesting_cliff: u64,
+    pub vesting_duration: u64,
+    // longest lock of a stake account, full voting power; 0 to disable locks
+    pub max_lock_duration: u64,
+    // rewards emitted to stakers, in stake and vesting accounts, not transferred out yet
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::decimal_string")
+    )]
+    pub owed_reward_wads: Decimal,
+    // `owed_reward_wads` was tracked from the start, unset for older pools where it misses
+    // the rewards owed before
+    pub owed_reward_tracked: bool,
+    // since rust on implement traits for array from 0..33 len
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub reserve_fields1: [u8; 0],
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub reserve_fields2: [u8; 0],
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub reserve_fields3: [u8; 0],
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub reserve_fields4: [u8; 8],
+}
+
+impl StakingPool {
+    /// Current time of the clock in the time basis of this pool.
+    pub fn current_time(&self, clock: &Clock) -> Result<u64, ProgramError> {
+        self.time_basis.current_time(clock)
+    }
+
+    /// Accrue rewards into `cumulative_rate` from `start_time` up to `current_time`, capped at
+    /// `end_time`.
+    pub fn update(&mut self, current_time: Slot) -> ProgramResult {
+        if current_time < self.start_time {
+            return Ok(());
+        }
+        let current_time = min(current_time, self.end_time);
+        if current_time < self.last_update {
+            msg!("Current time is earlier than the last pool update");
+            return Err(StakingError::InvalidCurrentTimeError.into());
+        }
+        let time_elapsed = current_time.saturating_sub(max(self.last_update, self.start_time));
+        let reward = self.rate_per_slot.try_mul(time_elapsed)?;
+        self.allocate_reward(reward)?;
+        self.last_update = current_time;
+        Ok(())
+    }
+
+    /// Share `reward` among the current stakers, or keep it unallocated when there are none.
+    fn allocate_reward(&mut self, reward: Decimal) -> ProgramResult {
+        if self.pool_size != 0 {
+            self.cumulative_rate = self
+                .cumulative_rate
+                .try_add(reward.try_div(self.pool_size)?)?;
+            self.owed_reward_wads = self.owed_reward_wads.try_add(reward)?;
+        } else {
+            self.unallocated_reward_wads = self.unallocated_reward_wads.try_add(reward)?;
+        }
+        Ok(())
+    }
+
+    /// Update the pool to `current_time` and emit at `rate_per_slot` from then until `end_time`.
+    pub fn set_rate_per_slot(
+        &mut self,
+        rate_per_slot: Decimal,
+        end_time: Slot,
+        current_time: Slot,
+    ) -> ProgramResult {
+        if end_time < current_time {
+            msg!("End time is earlier than the current time");
+            return Err(StakingError::InvalidArgumentError.into());
+        }
+        self.update(current_time)?;
+        // nothing accrues for the time since a past end time
+        self.last_update = max(self.last_update, current_time);
+        self.rate_per_slot = rate_per_slot;
+        self.end_time = end_time;
+        Ok(())
+    }
+
+    /// Update the pool to `current_time` and share `amount` pulled from a reward distributor
+    /// among the current stakers.
+    pub fn receive_distributed_reward(&mut self, amount: u64, current_time: Slot) -> ProgramResult {
+        self.update(current_time)?;
+        self.allocate_reward(Decimal::from(amount))
+    }
+
+    /// Return rewards that are no longer owed to any staker to the pool.
+    pub fn forfeit_reward(&mut self, reward_wads: Decimal) -> ProgramResult {
+        self.unallocated_reward_wads = self.unallocated_reward_wads.try_add(reward_wads)?;
+        self.release_owed_reward(reward_wads)
+    }
+
+    /// Record `amount` of owed rewards transferred out of the reward token pool.
+    pub fn pay_reward(&mut self, amount: u64) -> ProgramResult {
+        self.release_owed_reward(Decimal::from(amount))
+    }
+
+    // Saturates in pools without `owed_reward_tracked`, which miss the rewards owed before.
+    fn release_owed_reward(&mut self, reward_wads: Decimal) -> ProgramResult {
+        self.owed_reward_wads = if reward_wads < self.owed_reward_wads {
+            self.owed_reward_wads.try_sub(reward_wads)?
+        } else {
+            Decimal::zero()
+        };
+        Ok(())
+    }
+
+    /// Set the early claim penalty, at most 10000 basis points.
+    pub fn set_early_claim_penalty(
+        &mut self,
+        penalty_bps: u16,
+        mode: EarlyClaimPenaltyMode,
+    ) -> ProgramResult {
+        if penalty_bps > MAX_BPS {
+            msg!("Early claim penalty must be at most 10000 basis points");
+            return Err(StakingError::InvalidArgumentError.into());
+        }
+        self.early_claim_penalty_bps = penalty_bps;
+        self.early_claim_penalty_mode = mode;
+        Ok(())
+    }
+
+    /// Part of `reward` forfeited when claiming it at `current_time`.
+    pub fn early_claim_penalty(
+        &self,
+        reward: u64,
+        current_time: Slot,
+    ) -> Result<u64, ProgramError> {
+        if current_time >= self.earliest_reward_claim_time {
+            return Ok(0);
+        }
+        if self.early_claim_penalty_bps == 0 {
+            msg!("Rewards cannot be claimed before the earliest reward claim time");
+            return Err(StakingError::InvalidCurrentTimeError.into());
+        }
+        let remaining = self.earliest_reward_claim_time - current_time;
+        let window = self
+            .earliest_reward_claim_time
+            .saturating_sub(self.reward_start_time())
+            .max(remaining);
+        let penalty = (reward as u128)
+            .checked_mul(self.early_claim_penalty_bps as u128)
+            .and_then(|penalty| penalty.checked_mul(remaining as u128))
+            .ok_or(StakingError::MathOverflow)?
+            / (MAX_BPS as u128 * window as u128);
+        u64::try_from(penalty).map_err(|_| StakingError::MathOverflow.into())
+    }
+
+    /// Start of the current reward period, `start_time` or, for pools created without one, the
+    /// end time less the duration.
+    pub fn reward_start_time(&self) -> Slot {
+        if self.start_time != 0 {
+            self.start_time
+        } else {
+            self.end_time.saturating_sub(self.duration)
+        }
+    }
+
+    /// Hand `penalty` forfeited by an early claim over according to the penalty mode. Redistributed
+    /// penalties go to the stake other than the `claimer_deposit` of the claimer, who must move
+    /// its `start_rate` up to the new cumulative rate, and back to the pool when there is none.
+    pub fn forfeit_claim_penalty(&mut self, penalty: u64, claimer_deposit: u64) -> ProgramResult {
+        let penalty_wads = Decimal::from(penalty);
+        let other_stake = self.pool_size.saturating_sub(claimer_deposit);
+        match self.early_claim_penalty_mode {
+            EarlyClaimPenaltyMode::Redistribute if other_stake != 0 => {
+                self.cumulative_rate = self
+                    .cumulative_rate
+                    .try_add(penalty_wads.try_div(other_stake)?)?;
+                Ok(())
+            }
+            _ => self.forfeit_reward(penalty_wads),
+        }
+    }
+
+    /// Set the vesting schedule of claimed rewards, 0 duration for liquid claims.
+    pub fn set_vesting_schedule(&mut self, cliff: u64, duration: u64) -> ProgramResult {
+        if cliff > duration {
+            msg!("Vesting cliff must not be longer than the vesting duration");
+            return Err(StakingError::InvalidArgumentError.into());
+        }
+        self.vesting_cliff = cliff;
+        self.vesting_duration = duration;
+        Ok(())
+    }
+
+    /// Whether claimed rewards go to vesting accounts instead of being transferred.
+    pub fn vests_rewards(&self) -> bool {
+        self.vesting_duration != 0
+    }
+
+    /// Check that the pool accepts deposits at `current_time`.
+    pub fn check_deposit_allowed(&self, current_time: Slot) -> ProgramResult {
+        if current_time < self.start_time && !self.deposit_before_start {
+            msg!("Staking pool does not accept deposits before its start time");
+            return Err(StakingError::StakingPoolNotStarted.into());
+        }
+        Ok(())
+    }
+
+    /// Switch this pool to holding deposits in its stake vault, only while it holds none.
+    pub fn enable_custody(&mut self) -> ProgramResult {
+        if self.custodial {
+            msg!("Staking pool already has a stake vault");
+            return Err(StakingError::AlreadyInitialized.into());
+        }
+        if self.pool_size != 0 {
+            msg!("Staking pool still has deposits");
+            return Err(StakingError::StakingPoolNotEmpty.into());
+        }
+        self.custodial = true;
+        Ok(())
+    }
+
+    /// Check that depositing `amount` into a stake account already holding
+    /// `deposited_amount` stays within the deposit limits of the pool.
+    pub fn check_deposit_limits(&self, deposited_amount: u64, amount: u64) -> ProgramResult {
+        let pool_size = self
+            .pool_size
+            .checked_add(amount)
+            .ok_or(StakingError::MathOverflow)?;
+        if self.max_pool_size != 0 && pool_size > self.max_pool_size {
+            msg!("Deposit would exceed the maximum size of the staking pool");
+            return Err(StakingError::PoolSizeLimitExceeded.into());
+        }
+        let deposited_amount = deposited_amount
+            .checked_add(amount)
+            .ok_or(StakingError::MathOverflow)?;
+        if self.max_deposit_per_account != 0 && deposited_amount > self.max_deposit_per_account {
+            msg!("Deposit would exceed the maximum deposit of a stake account");
+            return Err(StakingError::AccountDepositLimitExceeded.into());
+        }
+        Ok(())
+    }
+
+    /// Check that `owner` may create a stake account in this pool, at address `staking_pool`:
+    /// always when the allowlist is disabled, otherwise when the gatekeeper signed or with an
+    /// allowlist entry for the owner.
+    pub fn check_allowlisted(
+        &self,
+        staking_pool: &Pubkey,
+        owner: &Pubkey,
+        gatekeeper_signer: Option<&Pubkey>,
+        allowlist_entry: Option<&AllowlistEntry>,
+    ) -> ProgramResult {
+        if !self.allowlist_enabled {
+            return Ok(());
+        }
+        let gatekeeper_signed =
+            self.gatekeeper != Pubkey::default() && gatekeeper_signer == Some(&self.gatekeeper);
+        let allowlisted =
+            matches!(allowlist_entry, Some(entry) if entry.allows(staking_pool, owner));
+        if !gatekeeper_signed && !allowlisted {
+            msg!("Stake account owner is not allowlisted in the staking pool");
+            return Err(StakingError::NotAllowlisted.into());
+        }
+        Ok(())
+    }
+
+    /// Finalise the ended reward period and start emitting `supply` over `duration` from
+    /// `current_time`, keeping every stake account valid.
+    pub fn start_new_period(
+        &mut self,
+        supply: u64,
+        duration: u64,
+        current_time: Slot,
+    ) -> ProgramResult {
+        if supply == 0 {
+            msg!("Supply of the new reward period must be non zero");
+            return Err(StakingError::InvalidSupplyError.into());
+        }
+        if duration == 0 {
+            msg!("Duration of the new reward period must be non zero");
+            return Err(StakingError::InvalidDurationError.into());
+        }
+        if current_time < self.end_time {
+            msg!("A new reward period can only start after the current one ends");
+            return Err(StakingError::StakingPoolNotEnded.into());
+        }
+        self.update(current_time)?;
+        self.rate_per_slot = Decimal::from(supply).try_div(duration)?;
+        self.duration = duration;
+        self.start_time = current_time;
+        self.last_update = current_time;
+        self.end_time = current_time
+            .checked_add(duration)
+            .ok_or(StakingError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Check that the reward period has ended, no deposits remain and less than a whole token
+    /// of rewards is owed, so the pool can be closed.
+    pub fn check_closable(&self, current_time: Slot) -> ProgramResult {
+        if current_time < self.end_time {
+            msg!("Staking pool can only be closed after the reward period ends");
+            return Err(StakingError::StakingPoolNotEnded.into());
+        }
+        if self.pool_size != 0 {
+            msg!("Staking pool cannot be closed while deposits remain");
+            return Err(StakingError::StakingPoolNotEmpty.into());
+        }
+        self.check_owed_reward_tracked()?;
+        if self.owed_reward_wads.try_floor_u64()? != 0 {
+            msg!("Staking pool cannot be closed while rewards are owed to stakers");
+            return Err(StakingError::StakingPoolNotEmpty.into());
+        }
+        Ok(())
+    }
+
+    /// Check that `owed_reward_wads` holds every reward owed to stakers, so the rest of the
+    /// reward token pool can be taken.
+    pub fn check_owed_reward_tracked(&self) -> ProgramResult {
+        if !self.owed_reward_tracked {
+            msg!("Staking pool predates tracking the rewards owed to stakers");
+            return Err(StakingError::InvalidStakingPool.into());
+        }
+        Ok(())
+    }
+
+    /// Take everything in the reward token pool, holding `reward_token_pool_balance`, beyond the
+    /// whole tokens owed to stakers once the reward period has ended, returning the amount to
+    /// transfer out. Includes rounding dust and tokens sent to the reward token pool directly.
+    pub fn withdraw_unallocated_rewards(
+        &mut self,
+        reward_token_pool_balance: u64,
+        current_time: Slot,
+    ) -> Result<u64, ProgramError> {
+        if current_time < self.end_time {
+            msg!("Unallocated rewards can only be withdrawn after the reward period ends");
+            return Err(StakingError::StakingPoolNotEnded.into());
+        }
+        self.check_owed_reward_tracked()?;
+        self.update(current_time)?;
+        let amount =
+            reward_token_pool_balance.saturating_sub(self.owed_reward_wads.try_ceil_u64()?);
+        self.unallocated_reward_wads = Decimal::zero();
+        Ok(amount)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshSerialize for StakingPool {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.version.serialize(writer)?;
+        self.owner_authority.serialize(writer)?;
+        self.admin_authority.serialize(writer)?;
+        self.reward_token_pool.serialize(writer)?;
+        self.last_update.serialize(writer)?;
+        self.end_time.serialize(writer)?;
+        self.earliest_reward_claim_time.serialize(writer)?;
+        self.duration.serialize(writer)?;
+        decimal_borsh::serialize(&self.rate_per_slot, writer)?;
+        decimal_borsh::serialize(&self.cumulative_rate, writer)?;
+        self.pool_size.serialize(writer)?;
+        self.bump_seed_staking_program.serialize(writer)?;
+        decimal_borsh::serialize(&self.unallocated_reward_wads, writer)?;
+        self.start_time.serialize(writer)?;
+        self.deposit_before_start.serialize(writer)?;
+        self.time_basis.serialize(writer)?;
+        self.max_pool_size.serialize(writer)?;
+        self.max_deposit_per_account.serialize(writer)?;
+        self.allowlist_enabled.serialize(writer)?;
+        self.gatekeeper.serialize(writer)?;
+        self.custodial.serialize(writer)?;
+        self.early_claim_penalty_bps.serialize(writer)?;
+        self.early_claim_penalty_mode.serialize(writer)?;
+        self.vesting_cliff.serialize(writer)?;
+        self.vesting_duration.serialize(writer)?;
+        self.max_lock_duration.serialize(writer)?;
+        decimal_borsh::serialize(&self.owed_reward_wads, writer)?;
+        self.owed_reward_tracked.serialize(writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl BorshDeserialize for StakingPool {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        Ok(Self {
+            version: u8::deserialize(buf)?,
+            owner_authority: Pubkey::deserialize(buf)?,
+            admin_authority: Pubkey::deserialize(buf)?,
+            reward_token_pool: Pubkey::deserialize(buf)?,
+            last_update: Slot::deserialize(buf)?,
+            end_time: Slot::deserialize(buf)?,
+            earliest_reward_claim_time: Slot::deserialize(buf)?,
+            duration: u64::deserialize(buf)?,
+            rate_per_slot: decimal_borsh::deserialize(buf)?,
+            cumulative_rate: decimal_borsh::deserialize(buf)?,
+            pool_size: u64::deserialize(buf)?,
+            bump_seed_staking_program: u8::deserialize(buf)?,
+            unallocated_reward_wads: decimal_borsh::deserialize(buf)?,
+            start_time: Slot::deserialize(buf)?,
+            deposit_before_start: bool::deserialize(buf)?,
+            time_basis: TimeBasis::deserialize(buf)?,
+            max_pool_size: u64::deserialize(buf)?,
+            max_deposit_per_account: u64::deserialize(buf)?,
+            allowlist_enabled: bool::deserialize(buf)?,
+            gatekeeper: Pubkey::deserialize(buf)?,
+            custodial: bool::deserialize(buf)?,
+            early_claim_penalty_bps: u16::deserialize(buf)?,
+            early_claim_penalty_mode: EarlyClaimPenaltyMode::deserialize(buf)?,
+            vesting_cliff: u64::deserialize(buf)?,
+            vesting_duration: u64::deserialize(buf)?,
+            max_lock_duration: u64::deserialize(buf)?,
+            owed_reward_wads: decimal_borsh::deserialize(buf)?,
+            owed_reward_tracked: bool::deserialize(buf)?,
+            ..Self::default()
+        })
+    }
 }
 
 impl Sealed for StakingPool {}
@@ -49,7 +600,23 @@ impl Pack for StakingPool {
         + Decimal::LEN
         + 8
         + 1
-        + 128;
+        + Decimal::LEN
+        + 8
+        + 1
+        + 1
+        + 8
+        + 8
+        + 1
+        + PUBKEY_BYTES
+        + 1
+        + 2
+        + 1
+        + 8
+        + 8
+        + 8
+        + Decimal::LEN
+        + 1
+        + 8;
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
         let output = array_mut_ref![dst, 0, StakingPool::LEN];
@@ -67,6 +634,22 @@ impl Pack for StakingPool {
             cumulative_rate,
             pool_size,
             bump_seed_staking_program,
+            unallocated_reward_wads,
+            start_time,
+            deposit_before_start,
+            time_basis,
+            max_pool_size,
+            max_deposit_per_account,
+            allowlist_enabled,
+            gatekeeper,
+            custodial,
+            early_claim_penalty_bps,
+            early_claim_penalty_mode,
+            vesting_cliff,
+            vesting_duration,
+            max_lock_duration,
+            owed_reward_wads,
+            owed_reward_tracked,
             _,
         ) = mut_array_refs![
             output,
@@ -82,7 +665,23 @@ impl Pack for StakingPool {
             Decimal::LEN,
             8,
             1,
-            128
+            Decimal::LEN,
+            8,
+            1,
+            1,
+            8,
+            8,
+            1,
+            PUBKEY_BYTES,
+            1,
+            2,
+            1,
+            8,
+            8,
+            8,
+            Decimal::LEN,
+            1,
+            8
         ];
         *version = self.version.to_le_bytes();
         owner_authority.copy_from_slice(self.owner_authority.as_ref());
@@ -96,6 +695,23 @@ impl Pack for StakingPool {
         self.cumulative_rate.pack_into_slice(cumulative_rate);
         *pool_size = self.pool_size.to_le_bytes();
         *bump_seed_staking_program = self.bump_seed_staking_program.to_le_bytes();
+        self.unallocated_reward_wads
+            .pack_into_slice(unallocated_reward_wads);
+        *start_time = self.start_time.to_le_bytes();
+        *deposit_before_start = [self.deposit_before_start as u8];
+        *time_basis = [self.time_basis as u8];
+        *max_pool_size = self.max_pool_size.to_le_bytes();
+        *max_deposit_per_account = self.max_deposit_per_account.to_le_bytes();
+        *allowlist_enabled = [self.allowlist_enabled as u8];
+        gatekeeper.copy_from_slice(self.gatekeeper.as_ref());
+        *custodial = [self.custodial as u8];
+        *early_claim_penalty_bps = self.early_claim_penalty_bps.to_le_bytes();
+        *early_claim_penalty_mode = [self.early_claim_penalty_mode as u8];
+        *vesting_cliff = self.vesting_cliff.to_le_bytes();
+        *vesting_duration = self.vesting_duration.to_le_bytes();
+        *max_lock_duration = self.max_lock_duration.to_le_bytes();
+        self.owed_reward_wads.pack_into_slice(owed_reward_wads);
+        *owed_reward_tracked = [self.owed_reward_tracked as u8];
     }
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
         let input = array_ref![src, 0, StakingPool::LEN];
@@ -113,6 +729,22 @@ impl Pack for StakingPool {
             cumulative_rate,
             pool_size,
             bump_seed_staking_program,
+            unallocated_reward_wads,
+            start_time,
+            deposit_before_start,
+            time_basis,
+            max_pool_size,
+            max_deposit_per_account,
+            allowlist_enabled,
+            gatekeeper,
+            custodial,
+            early_claim_penalty_bps,
+            early_claim_penalty_mode,
+            vesting_cliff,
+            vesting_duration,
+            max_lock_duration,
+            owed_reward_wads,
+            owed_reward_tracked,
             _,
         ) = array_refs![
             input,
@@ -128,7 +760,23 @@ impl Pack for StakingPool {
             Decimal::LEN,
             8,
             1,
-            128
+            Decimal::LEN,
+            8,
+            1,
+            1,
+            8,
+            8,
+            1,
+            PUBKEY_BYTES,
+            1,
+            2,
+            1,
+            8,
+            8,
+            8,
+            Decimal::LEN,
+            1,
+            8
         ];
         let version = u8::from_le_bytes(*version);
         if version > PROGRAM_VERSION {
@@ -146,7 +794,23 @@ impl Pack for StakingPool {
         let cumulative_rate = Decimal::unpack_from_slice(cumulative_rate)?;
         let pool_size = u64::from_le_bytes(*pool_size);
         let bump_seed_staking_program = u8::from_le_bytes(*bump_seed_staking_program);
-        let reserve_field = [0; 32];
+        let unallocated_reward_wads = Decimal::unpack_from_slice(unallocated_reward_wads)?;
+        let start_time = Slot::from_le_bytes(*start_time);
+        let deposit_before_start = unpack_bool(deposit_before_start)?;
+        let time_basis = TimeBasis::try_from(u8::from_le_bytes(*time_basis))?;
+        let max_pool_size = u64::from_le_bytes(*max_pool_size);
+        let max_deposit_per_account = u64::from_le_bytes(*max_deposit_per_account);
+        let allowlist_enabled = unpack_bool(allowlist_enabled)?;
+        let gatekeeper = Pubkey::new_from_array(*gatekeeper);
+        let custodial = unpack_bool(custodial)?;
+        let early_claim_penalty_bps = u16::from_le_bytes(*early_claim_penalty_bps);
+        let early_claim_penalty_mode =
+            EarlyClaimPenaltyMode::try_from(u8::from_le_bytes(*early_claim_penalty_mode))?;
+        let vesting_cliff = u64::from_le_bytes(*vesting_cliff);
+        let vesting_duration = u64::from_le_bytes(*vesting_duration);
+        let max_lock_duration = u64::from_le_bytes(*max_lock_duration);
+        let owed_reward_wads = Decimal::unpack_from_slice(owed_reward_wads)?;
+        let owed_reward_tracked = unpack_bool(owed_reward_tracked)?;
         Ok(StakingPool {
             version,
             owner_authority,
@@ -160,10 +824,453 @@ impl Pack for StakingPool {
             cumulative_rate,
             pool_size,
             bump_seed_staking_program,
-            reserve_fields1: reserve_field,
-            reserve_fields2: reserve_field,
-            reserve_fields3: reserve_field,
-            reserve_fields4: reserve_field,
+            unallocated_reward_wads,
+            start_time,
+            deposit_before_start,
+            time_basis,
+            max_pool_size,
+            max_deposit_per_account,
+            allowlist_enabled,
+            gatekeeper,
+            custodial,
+            early_claim_penalty_bps,
+            early_claim_penalty_mode,
+            vesting_cliff,
+            vesting_duration,
+            max_lock_duration,
+            owed_reward_wads,
+            owed_reward_tracked,
+            reserve_fields1: [],
+            reserve_fields2: [],
+            reserve_fields3: [],
+            reserve_fields4: [0; 8],
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::state::fixtures::staking_pool;
+    use crate::state::PROGRAM_VERSION;
+
+    #[test]
+    fn update_tracks_owed_and_unallocated_rewards() {
+        let mut pool = staking_pool(0);
+        pool.update(100).unwrap();
+        pool.pool_size = 3;
+        pool.update(1_000).unwrap();
+
+        assert_eq!(pool.unallocated_reward_wads, Decimal::from(1_000u64));
+        assert_eq!(pool.owed_reward_wads, Decimal::from(9_000u64));
+    }
+
+    #[test]
+    fn paid_and_forfeited_rewards_are_no_longer_owed() {
+        let mut pool = staking_pool(10);
+        pool.update(100).unwrap();
+        pool.pay_reward(300).unwrap();
+        pool.forfeit_reward(Decimal::from(200u64)).unwrap();
+
+        assert_eq!(pool.owed_reward_wads, Decimal::from(500u64));
+        assert_eq!(pool.unallocated_reward_wads, Decimal::from(200u64));
+
+        // rewards accrued before the pool tracked them
+        pool.pay_reward(1_000).unwrap();
+        assert_eq!(pool.owed_reward_wads, Decimal::zero());
+    }
+
+    #[test]
+    fn withdraw_unallocated_rewards_takes_everything_not_owed() {
+        let mut pool = staking_pool(0);
+        pool.update(100).unwrap();
+        pool.pool_size = 3;
+
+        assert_eq!(
+            pool.withdraw_unallocated_rewards(10_000, 999),
+            Err(StakingError::StakingPoolNotEnded.into())
+        );
+        // 1,000 emitted into the empty pool and 50 sent to the reward token pool directly
+        assert_eq!(pool.withdraw_unallocated_rewards(10_050, 1_000), Ok(1_050));
+        assert_eq!(pool.unallocated_reward_wads, Decimal::zero());
+        assert_eq!(pool.owed_reward_wads, Decimal::from(9_000u64));
+        assert_eq!(pool.withdraw_unallocated_rewards(9_000, 1_100), Ok(0));
+    }
+
+    #[test]
+    fn withdraw_unallocated_rewards_holds_back_fractional_rewards() {
+        let mut pool = StakingPool {
+            end_time: 3,
+            duration: 3,
+            rate_per_slot: Decimal::from(1_000u64).try_div(3).unwrap(),
+            ..staking_pool(7)
+        };
+        pool.update(3).unwrap();
+
+        assert!(pool.owed_reward_wads < Decimal::from(1_000u64));
+        assert_eq!(pool.withdraw_unallocated_rewards(1_000, 3), Ok(0));
+        assert_eq!(pool.withdraw_unallocated_rewards(1_001, 3), Ok(1));
+    }
+
+    #[test]
+    fn check_closable_allows_reward_dust() {
+        let mut pool = staking_pool(0);
+        assert_eq!(
+            pool.check_closable(999),
+            Err(StakingError::StakingPoolNotEnded.into())
+        );
+        pool.pool_size = 1;
+        assert_eq!(
+            pool.check_closable(1_000),
+            Err(StakingError::StakingPoolNotEmpty.into())
+        );
+        pool.pool_size = 0;
+        pool.owed_reward_wads = Decimal::one();
+        assert_eq!(
+            pool.check_closable(1_000),
+            Err(StakingError::StakingPoolNotEmpty.into())
+        );
+        pool.owed_reward_wads = Decimal::from_scaled_val(999_999_999_999_999_999);
+        assert_eq!(pool.check_closable(1_000), Ok(()));
+    }
+
+    #[test]
+    fn start_new_period_emits_from_current_time() {
+        let mut pool = staking_pool(10);
+        pool.update(500).unwrap();
+        pool.start_new_period(3_000, 100, 1_200).unwrap();
+
+        // the gap between the periods emits nothing
+        let cumulative_rate = Decimal::from(1_000u64);
+        assert_eq!(pool.cumulative_rate, cumulative_rate);
+        assert_eq!(pool.rate_per_slot, Decimal::from(30u64));
+        assert_eq!(
+            (
+                pool.start_time,
+                pool.last_update,
+                pool.end_time,
+                pool.duration
+            ),
+            (1_200, 1_200, 1_300, 100)
+        );
+
+        pool.update(1_400).unwrap();
+        assert_eq!(
+            pool.cumulative_rate,
+            cumulative_rate.try_add(Decimal::from(300u64)).unwrap()
+        );
+    }
+
+    #[test]
+    fn start_new_period_rejects_invalid_periods() {
+        let mut pool = staking_pool(10);
+        assert_eq!(
+            pool.start_new_period(3_000, 100, 999),
+            Err(StakingError::StakingPoolNotEnded.into())
+        );
+        assert_eq!(
+            pool.start_new_period(0, 100, 1_000),
+            Err(StakingError::InvalidSupplyError.into())
+        );
+        assert_eq!(
+            pool.start_new_period(3_000, 0, 1_000),
+            Err(StakingError::InvalidDurationError.into())
+        );
+        assert_eq!(pool, staking_pool(10));
+    }
+
+    #[test]
+    fn update_accrues_from_start_time() {
+        let mut pool = StakingPool {
+            start_time: 400,
+            end_time: 1_400,
+            last_update: 100,
+            ..staking_pool(10)
+        };
+        pool.update(300).unwrap();
+        assert_eq!(pool.cumulative_rate, Decimal::zero());
+        assert_eq!(pool.last_update, 100);
+
+        pool.update(500).unwrap();
+        assert_eq!(pool.cumulative_rate, Decimal::from(100u64));
+        pool.update(2_000).unwrap();
+        assert_eq!(pool.cumulative_rate, Decimal::from(1_000u64));
+        assert_eq!(pool.last_update, 1_400);
+    }
+
+    #[test]
+    fn time_basis_reads_the_clock() {
+        let clock = Clock {
+            slot: 42,
+            unix_timestamp: 1_650_000_000,
+            ..Clock::default()
+        };
+        assert_eq!(TimeBasis::default(), TimeBasis::Slot);
+        assert_eq!(TimeBasis::Slot.current_time(&clock), Ok(42));
+        assert_eq!(
+            TimeBasis::UnixTimestamp.current_time(&clock),
+            Ok(1_650_000_000)
+        );
+
+        let clock = Clock {
+            unix_timestamp: -1,
+            ..clock
+        };
+        assert_eq!(
+            TimeBasis::UnixTimestamp.current_time(&clock),
+            Err(StakingError::InvalidCurrentTimeError.into())
+        );
+    }
+
+    #[test]
+    fn time_basis_round_trip() {
+        for &time_basis in &[TimeBasis::Slot, TimeBasis::UnixTimestamp] {
+            assert_eq!(TimeBasis::try_from(time_basis as u8), Ok(time_basis));
+        }
+        assert_eq!(
+            TimeBasis::try_from(2),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn check_deposit_limits_enforces_both_limits() {
+        let pool = StakingPool {
+            max_pool_size: 1_000,
+            max_deposit_per_account: 300,
+            ..staking_pool(800)
+        };
+        assert_eq!(pool.check_deposit_limits(100, 200), Ok(()));
+        assert_eq!(
+            pool.check_deposit_limits(100, 201),
+            Err(StakingError::PoolSizeLimitExceeded.into())
+        );
+        let pool = StakingPool {
+            max_pool_size: 1_000,
+            max_deposit_per_account: 300,
+            ..staking_pool(0)
+        };
+        assert_eq!(
+            pool.check_deposit_limits(100, 201),
+            Err(StakingError::AccountDepositLimitExceeded.into())
+        );
+        assert_eq!(
+            pool.check_deposit_limits(u64::MAX, 1),
+            Err(StakingError::MathOverflow.into())
+        );
+    }
+
+    #[test]
+    fn check_deposit_limits_ignores_zero_limits() {
+        let pool = staking_pool(1_000_000);
+        assert_eq!(pool.check_deposit_limits(1_000_000, 1_000_000), Ok(()));
+        assert_eq!(
+            staking_pool(u64::MAX).check_deposit_limits(0, 1),
+            Err(StakingError::MathOverflow.into())
+        );
+    }
+
+    #[test]
+    fn check_allowlisted_accepts_gatekeeper_or_entry() {
+        let staking_pool_key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let gatekeeper = Pubkey::new_unique();
+        let entry = AllowlistEntry {
+            version: PROGRAM_VERSION,
+            staking_pool: staking_pool_key,
+            owner,
+            ..AllowlistEntry::default()
+        };
+        let other_entry = AllowlistEntry {
+            owner: Pubkey::new_unique(),
+            ..entry.clone()
+        };
+        let uninitialized_entry = AllowlistEntry::default();
+        let no_gatekeeper = Pubkey::default();
+        let mut pool = StakingPool {
+            allowlist_enabled: true,
+            gatekeeper,
+            ..staking_pool(0)
+        };
+        let check = |pool: &StakingPool, signer, entry| {
+            pool.check_allowlisted(&staking_pool_key, &owner, signer, entry)
+        };
+
+        assert_eq!(check(&pool, Some(&gatekeeper), None), Ok(()));
+        assert_eq!(check(&pool, None, Some(&entry)), Ok(()));
+        assert_eq!(
+            check(&pool, Some(&owner), Some(&other_entry)),
+            Err(StakingError::NotAllowlisted.into())
+        );
+        assert_eq!(
+            check(&pool, None, Some(&uninitialized_entry)),
+            Err(StakingError::NotAllowlisted.into())
+        );
+
+        // no gatekeeper, the default pubkey never signs for it
+        pool.gatekeeper = Pubkey::default();
+        assert_eq!(
+            check(&pool, Some(&no_gatekeeper), None),
+            Err(StakingError::NotAllowlisted.into())
+        );
+        pool.allowlist_enabled = false;
+        assert_eq!(check(&pool, None, None), Ok(()));
+    }
+
+    #[test]
+    fn enable_custody_only_once_on_empty_pool() {
+        let mut pool = staking_pool(10);
+        assert_eq!(
+            pool.enable_custody(),
+            Err(StakingError::StakingPoolNotEmpty.into())
+        );
+
+        let mut pool = staking_pool(0);
+        assert_eq!(pool.enable_custody(), Ok(()));
+        assert!(pool.custodial);
+        assert_eq!(
+            pool.enable_custody(),
+            Err(StakingError::AlreadyInitialized.into())
+        );
+    }
+
+    #[test]
+    fn stake_vault_address_is_derived_from_pool() {
+        let program_id = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let (vault, bump_seed) = find_stake_vault_address(&program_id, &pool);
+
+        assert_eq!(
+            Pubkey::create_program_address(
+                &[STAKE_VAULT_SEED, pool.as_ref(), &[bump_seed]],
+                &program_id
+            ),
+            Ok(vault)
+        );
+        assert_ne!(
+            find_stake_vault_address(&program_id, &Pubkey::new_unique()).0,
+            vault
+        );
+    }
+
+    #[test]
+    fn early_claim_penalty_decreases_linearly() {
+        let mut pool = StakingPool {
+            start_time: 100,
+            earliest_reward_claim_time: 500,
+            ..staking_pool(10)
+        };
+        assert_eq!(
+            pool.early_claim_penalty(1_000, 200),
+            Err(StakingError::InvalidCurrentTimeError.into())
+        );
+
+        pool.set_early_claim_penalty(5_000, EarlyClaimPenaltyMode::Redistribute)
+            .unwrap();
+        assert_eq!(pool.early_claim_penalty(1_000, 100), Ok(500));
+        assert_eq!(pool.early_claim_penalty(1_000, 300), Ok(250));
+        assert_eq!(pool.early_claim_penalty(1_000, 500), Ok(0));
+        // before the start the penalty is the whole share
+        assert_eq!(pool.early_claim_penalty(1_000, 0), Ok(500));
+        assert_eq!(
+            pool.set_early_claim_penalty(10_001, EarlyClaimPenaltyMode::ReturnToPool),
+            Err(StakingError::InvalidArgumentError.into())
+        );
+    }
+
+    #[test]
+    fn early_claim_penalty_window_starts_at_reward_period_without_start_time() {
+        // created without a start time, the reward period started at 600
+        let mut pool = StakingPool {
+            end_time: 1_600,
+            earliest_reward_claim_time: 1_000,
+            ..staking_pool(10)
+        };
+        pool.set_early_claim_penalty(10_000, EarlyClaimPenaltyMode::Redistribute)
+            .unwrap();
+
+        assert_eq!(pool.reward_start_time(), 600);
+        assert_eq!(pool.early_claim_penalty(1_000, 600), Ok(1_000));
+        assert_eq!(pool.early_claim_penalty(1_000, 800), Ok(500));
+    }
+
+    #[test]
+    fn early_claim_penalty_overflow_is_an_error() {
+        let mut pool = StakingPool {
+            start_time: 0,
+            earliest_reward_claim_time: u64::MAX,
+            ..staking_pool(10)
+        };
+        pool.set_early_claim_penalty(10_000, EarlyClaimPenaltyMode::Redistribute)
+            .unwrap();
+
+        assert_eq!(
+            pool.early_claim_penalty(u64::MAX, 0),
+            Err(StakingError::MathOverflow.into())
+        );
+    }
+
+    #[test]
+    fn forfeit_claim_penalty_follows_mode() {
+        let mut pool = staking_pool(10);
+        pool.forfeit_claim_penalty(100, 0).unwrap();
+        assert_eq!(pool.cumulative_rate, Decimal::from(10u64));
+        assert_eq!(pool.unallocated_reward_wads, Decimal::zero());
+
+        // spread over the stake of everyone but the claimer
+        pool.forfeit_claim_penalty(100, 5).unwrap();
+        assert_eq!(pool.cumulative_rate, Decimal::from(30u64));
+        assert_eq!(pool.unallocated_reward_wads, Decimal::zero());
+
+        // back to the pool when the claimer holds the whole stake
+        pool.forfeit_claim_penalty(100, 10).unwrap();
+        assert_eq!(pool.cumulative_rate, Decimal::from(30u64));
+        assert_eq!(pool.unallocated_reward_wads, Decimal::from(100u64));
+
+        pool.early_claim_penalty_mode = EarlyClaimPenaltyMode::ReturnToPool;
+        pool.forfeit_claim_penalty(100, 0).unwrap();
+        assert_eq!(pool.cumulative_rate, Decimal::from(30u64));
+        assert_eq!(pool.unallocated_reward_wads, Decimal::from(200u64));
+        assert_eq!(
+            EarlyClaimPenaltyMode::default(),
+            EarlyClaimPenaltyMode::Redistribute
+        );
+    }
+
+    #[test]
+    fn set_rate_per_slot_emits_until_end_time() {
+        let mut pool = staking_pool(10);
+        pool.update(900).unwrap();
+        pool.set_rate_per_slot(Decimal::from(2u64), 1_200, 1_100)
+            .unwrap();
+        assert_eq!(pool.last_update, 1_100);
+        assert_eq!(pool.end_time, 1_200);
+        assert_eq!(pool.owed_reward_wads, Decimal::from(10_000u64));
+
+        // nothing accrues past the new end time
+        pool.update(5_000).unwrap();
+        assert_eq!(pool.owed_reward_wads, Decimal::from(10_200u64));
+        assert_eq!(
+            pool.set_rate_per_slot(Decimal::from(2u64), 4_999, 5_000),
+            Err(StakingError::InvalidArgumentError.into())
+        );
+    }
+
+    #[test]
+    fn untracked_pool_cannot_sweep_rewards_or_close() {
+        let mut pool = StakingPool {
+            owed_reward_tracked: false,
+            ..staking_pool(0)
+        };
+        assert_eq!(
+            pool.withdraw_unallocated_rewards(10_000, 1_000),
+            Err(StakingError::InvalidStakingPool.into())
+        );
+        assert_eq!(
+            pool.check_closable(1_000),
+            Err(StakingError::InvalidStakingPool.into())
+        );
+        assert_eq!(pool.last_update, 0);
+    }
+}
@@ -0,0 +1,324 @@
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::clock::Slot;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::pubkey::PUBKEY_BYTES;
+use std::convert::TryFrom;
+
+use crate::error::StakingError;
+use crate::solana_program::program_error::ProgramError;
+use crate::solana_program::program_pack::{IsInitialized, Pack, Sealed};
+use crate::solana_program::{msg, pubkey::Pubkey};
+use crate::state::{StakingPool, PROGRAM_VERSION, UNINITIALIZED_VERSION};
+
+/// Seed prefix of vesting account addresses, followed by the staking pool and the owner.
+pub const VESTING_SEED: &[u8] = b"vesting";
+
+/// Address of the vesting account of `owner` in `staking_pool`, with its bump seed.
+pub fn find_vesting_account_address(
+    program_id: &Pubkey,
+    staking_pool: &Pubkey,
+    owner: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[VESTING_SEED, staking_pool.as_ref(), owner.as_ref()],
+        program_id,
+    )
+}
+
+/// Rewards claimed from a vesting staking pool, released on the vesting schedule of the pool.
+///
+/// A top up moves `vesting_start` to the average of the current start and the top up time
+/// weighted by the amounts, leaving the amount vested at the top up unchanged. Once the whole
+/// schedule has vested, what is left is kept aside in `unlocked_amount` and the new rewards
+/// vest from the top up. The cliff only applies once, from the first top up.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct VestingAccount {
+    /// Version of the struct
+    pub version: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub staking_pool: Pubkey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::pubkey_base58"))]
+    pub owner: Pubkey,
+    pub bump_seed: u8,
+    // start of the current schedule
+    pub vesting_start: Slot,
+    // vesting in the current schedule
+    pub total_amount: u64,
+    // withdrawn from the current schedule
+    pub released_amount: u64,
+    // vested in previous schedules and not withdrawn yet
+    pub unlocked_amount: u64,
+    // first top up, start of the cliff; 0 for `vesting_start` in accounts topped up before
+    pub cliff_start: Slot,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub reserve_fields: [u8; 24],
+}
+
+impl VestingAccount {
+    /// Amount of the current schedule vested at `current_time`.
+    pub fn vested_amount(
+        &self,
+        staking_pool: &StakingPool,
+        current_time: Slot,
+    ) -> Result<u64, ProgramError> {
+        let cliff_start = if self.cliff_start != 0 {
+            self.cliff_start
+        } else {
+            self.vesting_start
+        };
+        if current_time.saturating_sub(cliff_start) < staking_pool.vesting_cliff {
+            return Ok(0);
+        }
+        let elapsed = current_time.saturating_sub(self.vesting_start);
+        if elapsed >= staking_pool.vesting_duration {
+            return Ok(self.total_amount);
+        }
+        let vested =
+            self.total_amount as u128 * elapsed as u128 / staking_pool.vesting_duration as u128;
+        u64::try_from(vested).map_err(|_| StakingError::MathOverflow.into())
+    }
+
+    /// Amount that can be withdrawn at `current_time`, none of the current schedule while a
+    /// longer vesting duration holds back more than was already released.
+    pub fn withdrawable_amount(
+        &self,
+        staking_pool: &StakingPool,
+        current_time: Slot,
+    ) -> Result<u64, ProgramError> {
+        self.vested_amount(staking_pool, current_time)?
+            .saturating_sub(self.released_amount)
+            .checked_add(self.unlocked_amount)
+            .ok_or_else(|| StakingError::MathOverflow.into())
+    }
+
+    /// Add `amount` of claimed rewards at `current_time`, vesting over the schedule of the pool
+    /// without delaying what has vested already.
+    pub fn top_up(
+        &mut self,
+        amount: u64,
+        staking_pool: &StakingPool,
+        current_time: Slot,
+    ) -> ProgramResult {
+        if self.cliff_start == 0 {
+            self.cliff_start = if self.total_amount == 0 {
+                current_time
+            } else {
+                self.vesting_start
+            };
+        }
+        let vested = self.vested_amount(staking_pool, current_time)?;
+        if vested == self.total_amount {
+            self.unlocked_amount = self
+                .total_amount
+                .saturating_sub(self.released_amount)
+                .checked_add(self.unlocked_amount)
+                .ok_or(StakingError::MathOverflow)?;
+            self.total_amount = amount;
+            self.released_amount = 0;
+            self.vesting_start = current_time;
+            return Ok(());
+        }
+        let total_amount = self
+            .total_amount
+            .checked_add(amount)
+            .ok_or(StakingError::MathOverflow)?;
+        let vesting_start = (u128::from(self.total_amount) * u128::from(self.vesting_start)
+            + u128::from(amount) * u128::from(current_time))
+            / u128::from(total_amount);
+        self.vesting_start = vesting_start as u64;
+        self.total_amount = total_amount;
+        Ok(())
+    }
+
+    /// Release everything vested at `current_time`, returning the amount to withdraw, recorded
+    /// as paid by `staking_pool`.
+    pub fn withdraw_vested(
+        &mut self,
+        staking_pool: &mut StakingPool,
+        current_time: Slot,
+    ) -> Result<u64, ProgramError> {
+        let amount = self.withdrawable_amount(staking_pool, current_time)?;
+        if amount == 0 {
+            msg!("Vesting account has nothing vested to withdraw");
+            return Err(StakingError::StakeWithdrawsZero.into());
+        }
+        self.released_amount = self
+            .released_amount
+            .max(self.vested_amount(staking_pool, current_time)?);
+        self.unlocked_amount = 0;
+        staking_pool.pay_reward(amount)?;
+        Ok(amount)
+    }
+}
+
+impl Sealed for VestingAccount {}
+impl IsInitialized for VestingAccount {
+    fn is_initialized(&self) -> bool {
+        self.version != UNINITIALIZED_VERSION
+    }
+}
+impl Pack for VestingAccount {
+    const LEN: usize = 1 + PUBKEY_BYTES + PUBKEY_BYTES + 1 + 8 + 8 + 8 + 8 + 8 + 24;
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let output = array_mut_ref![dst, 0, VestingAccount::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            staking_pool,
+            owner,
+            bump_seed,
+            vesting_start,
+            total_amount,
+            released_amount,
+            unlocked_amount,
+            cliff_start,
+            _,
+        ) = mut_array_refs![output, 1, PUBKEY_BYTES, PUBKEY_BYTES, 1, 8, 8, 8, 8, 8, 24];
+        *version = self.version.to_le_bytes();
+        staking_pool.copy_from_slice(self.staking_pool.as_ref());
+        owner.copy_from_slice(self.owner.as_ref());
+        *bump_seed = self.bump_seed.to_le_bytes();
+        *vesting_start = self.vesting_start.to_le_bytes();
+        *total_amount = self.total_amount.to_le_bytes();
+        *released_amount = self.released_amount.to_le_bytes();
+        *unlocked_amount = self.unlocked_amount.to_le_bytes();
+        *cliff_start = self.cliff_start.to_le_bytes();
+    }
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![src, 0, VestingAccount::LEN];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            staking_pool,
+            owner,
+            bump_seed,
+            vesting_start,
+            total_amount,
+            released_amount,
+            unlocked_amount,
+            cliff_start,
+            _,
+        ) = array_refs![input, 1, PUBKEY_BYTES, PUBKEY_BYTES, 1, 8, 8, 8, 8, 8, 24];
+        let version = u8::from_le_bytes(*version);
+        if version > PROGRAM_VERSION {
+            msg!("vesting account version does not match staking program version");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Self {
+            version,
+            staking_pool: Pubkey::new_from_array(*staking_pool),
+            owner: Pubkey::new_from_array(*owner),
+            bump_seed: u8::from_le_bytes(*bump_seed),
+            vesting_start: Slot::from_le_bytes(*vesting_start),
+            total_amount: u64::from_le_bytes(*total_amount),
+            released_amount: u64::from_le_bytes(*released_amount),
+            unlocked_amount: u64::from_le_bytes(*unlocked_amount),
+            cliff_start: Slot::from_le_bytes(*cliff_start),
+            reserve_fields: [0; 24],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::state::fixtures::staking_pool;
+    use crate::state::PROGRAM_VERSION;
+
+    fn vesting_pool(vesting_cliff: u64, vesting_duration: u64) -> StakingPool {
+        StakingPool {
+            vesting_cliff,
+            vesting_duration,
+            ..staking_pool(0)
+        }
+    }
+
+    fn vesting_account() -> VestingAccount {
+        VestingAccount {
+            version: PROGRAM_VERSION,
+            ..VestingAccount::default()
+        }
+    }
+
+    #[test]
+    fn vests_linearly_after_cliff() {
+        let pool = vesting_pool(100, 1_000);
+        let mut account = vesting_account();
+        account.top_up(1_000, &pool, 1_000).unwrap();
+
+        assert_eq!(account.withdrawable_amount(&pool, 1_099), Ok(0));
+        assert_eq!(account.withdrawable_amount(&pool, 1_100), Ok(100));
+        assert_eq!(account.withdraw_vested(&mut pool.clone(), 1_500), Ok(500));
+        assert_eq!(account.withdrawable_amount(&pool, 1_500), Ok(0));
+        assert_eq!(account.withdrawable_amount(&pool, 5_000), Ok(500));
+    }
+
+    #[test]
+    fn top_up_does_not_restart_cliff_or_vested_amount() {
+        let pool = vesting_pool(100, 1_000);
+        let mut account = vesting_account();
+        account.top_up(1_000, &pool, 1_000).unwrap();
+        account.top_up(1_000, &pool, 1_500).unwrap();
+
+        assert_eq!(account.vesting_start, 1_250);
+        assert_eq!(account.cliff_start, 1_000);
+        // vested amount is unchanged by the top up and keeps vesting past the cliff
+        assert_eq!(account.vested_amount(&pool, 1_500), Ok(500));
+        assert_eq!(account.vested_amount(&pool, 1_550), Ok(600));
+        assert_eq!(account.vested_amount(&pool, 2_250), Ok(2_000));
+    }
+
+    #[test]
+    fn top_up_after_full_vesting_starts_a_new_schedule() {
+        let pool = vesting_pool(100, 1_000);
+        let mut account = vesting_account();
+        account.top_up(1_000, &pool, 1_000).unwrap();
+        account.withdraw_vested(&mut pool.clone(), 1_500).unwrap();
+        account.top_up(1_000, &pool, 5_000).unwrap();
+
+        assert_eq!(account.unlocked_amount, 500);
+        assert_eq!(account.total_amount, 1_000);
+        assert_eq!(account.released_amount, 0);
+        assert_eq!(account.vesting_start, 5_000);
+        // the cliff has passed already
+        assert_eq!(account.withdrawable_amount(&pool, 5_010), Ok(510));
+        assert_eq!(account.withdrawable_amount(&pool, 6_000), Ok(1_500));
+    }
+
+    #[test]
+    fn longer_vesting_duration_holds_back_released_amount() {
+        let mut pool = vesting_pool(0, 1_000);
+        let mut account = vesting_account();
+        account.top_up(1_000, &pool, 0).unwrap();
+        assert_eq!(account.withdraw_vested(&mut pool.clone(), 800), Ok(800));
+
+        pool.set_vesting_schedule(0, 2_000).unwrap();
+        assert_eq!(account.withdrawable_amount(&pool, 1_000), Ok(0));
+        assert_eq!(
+            account.withdraw_vested(&mut pool.clone(), 1_000),
+            Err(StakingError::StakeWithdrawsZero.into())
+        );
+        assert_eq!(account.withdrawable_amount(&pool, 1_800), Ok(100));
+        assert_eq!(account.withdraw_vested(&mut pool, 1_800), Ok(100));
+        assert_eq!(account.released_amount, 900);
+    }
+
+    #[test]
+    fn withdraw_vested_is_recorded_as_paid() {
+        let mut pool = StakingPool {
+            owed_reward_wads: solana_maths::Decimal::from(1_000u64),
+            ..vesting_pool(0, 1_000)
+        };
+        let mut account = vesting_account();
+        account.top_up(1_000, &pool, 0).unwrap();
+
+        assert_eq!(account.withdraw_vested(&mut pool, 400), Ok(400));
+        assert_eq!(pool.owed_reward_wads, solana_maths::Decimal::from(600u64));
+    }
+}
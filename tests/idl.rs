@@ -0,0 +1,819 @@
+use port_staking_instructions::idl::{self, IdlAccountType, IdlInstruction, IdlType};
+use port_staking_instructions::instruction::{self, StakingInstruction};
+use port_staking_instructions::solana_program::instruction::Instruction;
+use port_staking_instructions::solana_program::program_pack::Pack;
+use port_staking_instructions::solana_program::pubkey::Pubkey;
+use port_staking_instructions::state::{
+    AllowlistEntry, DistributorPool, EarlyClaimPenaltyMode, Gauge, GaugePool, GaugeVote,
+    RewardDistributor, StakeAccount, StakingPool, TimeBasis, VestingAccount, MAX_DISTRIBUTOR_POOLS,
+    MAX_GAUGE_POOLS,
+};
+use solana_maths::Decimal;
+
+fn variant_name(ix: &StakingInstruction) -> &'static str {
+    match ix {
+        StakingInstruction::InitStakingPool { .. } => "InitStakingPool",
+        StakingInstruction::CreateStakeAccount => "CreateStakeAccount",
+        StakingInstruction::Deposit(_) => "Deposit",
+        StakingInstruction::Withdraw(_) => "Withdraw",
+        StakingInstruction::ClaimReward => "ClaimReward",
+        StakingInstruction::RefreshStakeAccount => "RefreshStakeAccount",
+        StakingInstruction::EmergencyWithdraw => "EmergencyWithdraw",
+        StakingInstruction::WithdrawUnallocatedRewards => "WithdrawUnallocatedRewards",
+        StakingInstruction::CloseStakingPool => "CloseStakingPool",
+        StakingInstruction::StartNewPeriod { .. } => "StartNewPeriod",
+        StakingInstruction::SetDepositLimits { .. } => "SetDepositLimits",
+        StakingInstruction::SetAllowlistConfig { .. } => "SetAllowlistConfig",
+        StakingInstruction::AddToAllowlist => "AddToAllowlist",
+        StakingInstruction::RemoveFromAllowlist => "RemoveFromAllowlist",
+        StakingInstruction::MergeStakeAccounts => "MergeStakeAccounts",
+        StakingInstruction::SplitStakeAccount { .. } => "SplitStakeAccount",
+        StakingInstruction::CompoundReward => "CompoundReward",
+        StakingInstruction::InitStakeVault => "InitStakeVault",
+        StakingInstruction::SetEarlyClaimPenalty { .. } => "SetEarlyClaimPenalty",
+        StakingInstruction::SetVestingSchedule { .. } => "SetVestingSchedule",
+        StakingInstruction::CreateVestingAccount => "CreateVestingAccount",
+        StakingInstruction::WithdrawVested => "WithdrawVested",
+        StakingInstruction::InitRewardDistributor { .. } => "InitRewardDistributor",
+        StakingInstruction::SetDistributorWeight { .. } => "SetDistributorWeight",
+        StakingInstruction::InitGauge { .. } => "InitGauge",
+        StakingInstruction::AddGaugePool => "AddGaugePool",
+        StakingInstruction::CreateGaugeVote => "CreateGaugeVote",
+        StakingInstruction::SetGaugeVote { .. } => "SetGaugeVote",
+        StakingInstruction::CommitGaugeVote => "CommitGaugeVote",
+        StakingInstruction::CrankGaugeEpoch => "CrankGaugeEpoch",
+        StakingInstruction::SetMaxLockDuration { .. } => "SetMaxLockDuration",
+        StakingInstruction::ExtendLock { .. } => "ExtendLock",
+        StakingInstruction::GetVotingPower { .. } => "GetVotingPower",
+        StakingInstruction::QueryPendingReward => "QueryPendingReward",
+    }
+}
+
+fn idl_instruction(name: &str) -> &'static IdlInstruction {
+    idl::INSTRUCTIONS
+        .iter()
+        .find(|ix| ix.name == name)
+        .unwrap_or_else(|| panic!("{} is missing from the IDL", name))
+}
+
+fn idl_account(name: &str) -> &'static IdlAccountType {
+    idl::ACCOUNTS
+        .iter()
+        .find(|account| account.name == name)
+        .unwrap_or_else(|| panic!("{} is missing from the IDL", name))
+}
+
+fn key() -> Pubkey {
+    Pubkey::new_unique()
+}
+
+fn built_instructions() -> Vec<Instruction> {
+    let program_id = key();
+    vec![
+        instruction::init_staking_pool_with_options(
+            program_id,
+            1_000,
+            100,
+            10,
+            5,
+            true,
+            TimeBasis::UnixTimestamp,
+            500,
+            50,
+            key(),
+            key(),
+            key(),
+            key(),
+            key(),
+            key(),
+            key(),
+        ),
+        instruction::create_stake_account(program_id, key(), key(), key()),
+        instruction::deposit(program_id, 7, key(), key(), key()),
+        instruction::withdraw(program_id, 7, key(), key(), key()),
+        instruction::claim_reward(program_id, key(), key(), key(), key(), key(), None, None),
+        instruction::refresh_stake_account(program_id, key(), key()),
+        instruction::emergency_withdraw(program_id, key(), key(), key()),
+        instruction::withdraw_unallocated_rewards(program_id, key(), key(), key(), key()),
+        instruction::close_staking_pool(program_id, key(), key(), key(), key(), key()),
+        instruction::start_new_period(program_id, 1_000, 100, key(), key(), key(), key(), key()),
+        instruction::set_deposit_limits(program_id, 1_000, 100, key(), key()),
+        instruction::set_allowlist_config(program_id, true, key(), key(), key()),
+        instruction::add_to_allowlist(program_id, key(), key(), key(), key()),
+        instruction::remove_from_allowlist(program_id, key(), key(), key(), key()),
+        instruction::merge_stake_accounts(program_id, key(), key(), key(), key(), key()),
+        instruction::split_stake_account(program_id, 7, Some(3), key(), key(), key(), key(), key()),
+        instruction::compound_reward(program_id, key(), key(), key(), key()),
+        instruction::init_stake_vault(program_id, key(), key(), key(), key()),
+        instruction::set_early_claim_penalty(
+            program_id,
+            2_500,
+            EarlyClaimPenaltyMode::ReturnToPool,
+            key(),
+            key(),
+        ),
+        instruction::set_vesting_schedule(program_id, 10, 100, key(), key()),
+        instruction::create_vesting_account(program_id, key(), key(), key()),
+        instruction::withdraw_vested(program_id, key(), key(), key(), key()),
+        instruction::init_reward_distributor(
+            program_id,
+            1_000,
+            100,
+            TimeBasis::Slot,
+            key(),
+            key(),
+            key(),
+            key(),
+            key(),
+            key(),
+        ),
+        instruction::set_distributor_weight(program_id, 3, key(), key(), key()),
+        instruction::init_gauge(
+            program_id,
+            100,
+            1_000,
+            TimeBasis::Slot,
+            key(),
+            key(),
+            key(),
+            key(),
+            key(),
+        ),
+        instruction::add_gauge_pool(program_id, key(), key(), key(), key(), key()),
+        instruction::create_gauge_vote(program_id, key(), key(), key(), key()),
+        instruction::set_gauge_vote(program_id, 1, 5_000, key(), key(), key()),
+        instruction::commit_gauge_vote(program_id, key(), key(), key(), key()),
+        instruction::crank_gauge_epoch(program_id, key(), key(), vec![]),
+        instruction::set_max_lock_duration(program_id, 1_000, key(), key()),
+        instruction::extend_lock(program_id, 500, key(), key(), key()),
+        instruction::get_voting_power(program_id, 100, key(), key()),
+        instruction::query_pending_reward(program_id, key(), key()),
+    ]
+}
+
+#[test]
+fn instruction_layouts_match_pack() {
+    let instructions = built_instructions();
+    assert_eq!(instructions.len(), idl::INSTRUCTIONS.len());
+
+    for built in instructions {
+        let unpacked = StakingInstruction::unpack(&built.data).unwrap();
+        let described = idl_instruction(variant_name(&unpacked));
+        assert_eq!(built.data[0], described.tag, "{}", described.name);
+        assert_eq!(built.data.len(), described.data_len(), "{}", described.name);
+        assert_eq!(unpacked.pack(), built.data, "{}", described.name);
+
+        let required: Vec<_> = described
+            .accounts
+            .iter()
+            .filter(|meta| !meta.is_optional)
+            .collect();
+        assert_eq!(built.accounts.len(), required.len(), "{}", described.name);
+        for (meta, described_meta) in built.accounts.iter().zip(required) {
+            assert_eq!(
+                meta.is_signer, described_meta.is_signer,
+                "{}",
+                described_meta.name
+            );
+            assert_eq!(
+                meta.is_writable, described_meta.is_writable,
+                "{}",
+                described_meta.name
+            );
+        }
+    }
+}
+
+fn u64_bytes(value: u64) -> Vec<u8> {
+    value.to_le_bytes().to_vec()
+}
+
+/// Every instruction with arguments, with the expected bytes of each argument in IDL order.
+fn instruction_args() -> Vec<(StakingInstruction, Vec<Vec<u8>>)> {
+    let (key1, key2) = (key(), key());
+    vec![
+        (
+            StakingInstruction::InitStakingPool {
+                supply: 1,
+                duration: 2,
+                earliest_reward_claim_time: 3,
+                bump_seed_staking_program: 4,
+                pool_owner_authority: key1,
+                admin_authority: key2,
+                start_time: 5,
+                deposit_before_start: true,
+                time_basis: TimeBasis::UnixTimestamp,
+                max_pool_size: 6,
+                max_deposit_per_account: 7,
+            },
+            vec![
+                u64_bytes(1),
+                u64_bytes(2),
+                u64_bytes(3),
+                vec![4],
+                key1.to_bytes().to_vec(),
+                key2.to_bytes().to_vec(),
+                u64_bytes(5),
+                vec![1],
+                vec![1],
+                u64_bytes(6),
+                u64_bytes(7),
+            ],
+        ),
+        (StakingInstruction::Deposit(8), vec![u64_bytes(8)]),
+        (StakingInstruction::Withdraw(9), vec![u64_bytes(9)]),
+        (
+            StakingInstruction::StartNewPeriod {
+                supply: 10,
+                duration: 11,
+            },
+            vec![u64_bytes(10), u64_bytes(11)],
+        ),
+        (
+            StakingInstruction::SetDepositLimits {
+                max_pool_size: 12,
+                max_deposit_per_account: 13,
+            },
+            vec![u64_bytes(12), u64_bytes(13)],
+        ),
+        (
+            StakingInstruction::SetAllowlistConfig {
+                allowlist_enabled: true,
+                gatekeeper: key1,
+            },
+            vec![vec![1], key1.to_bytes().to_vec()],
+        ),
+        (
+            StakingInstruction::SplitStakeAccount {
+                amount: 14,
+                reward_amount: Some(15),
+            },
+            vec![u64_bytes(14), [vec![1], u64_bytes(15)].concat()],
+        ),
+        (
+            StakingInstruction::SplitStakeAccount {
+                amount: 14,
+                reward_amount: None,
+            },
+            vec![u64_bytes(14), vec![0; 9]],
+        ),
+        (
+            StakingInstruction::SetEarlyClaimPenalty {
+                penalty_bps: 16,
+                mode: EarlyClaimPenaltyMode::ReturnToPool,
+            },
+            vec![16u16.to_le_bytes().to_vec(), vec![1]],
+        ),
+        (
+            StakingInstruction::SetVestingSchedule {
+                cliff: 17,
+                duration: 18,
+            },
+            vec![u64_bytes(17), u64_bytes(18)],
+        ),
+        (
+            StakingInstruction::InitRewardDistributor {
+                supply: 19,
+                duration: 20,
+                bump_seed: 21,
+                admin_authority: key1,
+                time_basis: TimeBasis::UnixTimestamp,
+            },
+            vec![
+                u64_bytes(19),
+                u64_bytes(20),
+                vec![21],
+                key1.to_bytes().to_vec(),
+                vec![1],
+            ],
+        ),
+        (
+            StakingInstruction::SetDistributorWeight { weight: 22 },
+            vec![u64_bytes(22)],
+        ),
+        (
+            StakingInstruction::InitGauge {
+                epoch_duration: 23,
+                emission_per_epoch: 24,
+                bump_seed: 25,
+                admin_authority: key2,
+                time_basis: TimeBasis::UnixTimestamp,
+            },
+            vec![
+                u64_bytes(23),
+                u64_bytes(24),
+                vec![25],
+                key2.to_bytes().to_vec(),
+                vec![1],
+            ],
+        ),
+        (
+            StakingInstruction::SetGaugeVote {
+                pool_index: 26,
+                weight_bps: 27,
+            },
+            vec![vec![26], 27u16.to_le_bytes().to_vec()],
+        ),
+        (
+            StakingInstruction::SetMaxLockDuration {
+                max_lock_duration: 28,
+            },
+            vec![u64_bytes(28)],
+        ),
+        (
+            StakingInstruction::ExtendLock { lock_end: 29 },
+            vec![u64_bytes(29)],
+        ),
+        (
+            StakingInstruction::GetVotingPower { time: 30 },
+            vec![u64_bytes(30)],
+        ),
+    ]
+}
+
+#[test]
+fn instruction_args_match_pack() {
+    let cases = instruction_args();
+    for described in idl::INSTRUCTIONS.iter().filter(|ix| !ix.args.is_empty()) {
+        assert!(
+            cases
+                .iter()
+                .any(|(ix, _)| variant_name(ix) == described.name),
+            "no expected arguments for {}",
+            described.name
+        );
+    }
+
+    for (ix, expected) in cases {
+        let described = idl_instruction(variant_name(&ix));
+        assert_eq!(described.args.len(), expected.len(), "{}", described.name);
+        let data = ix.pack();
+        assert_eq!(data[0], described.tag, "{}", described.name);
+        let mut offset = 1;
+        for (arg, bytes) in described.args.iter().zip(expected) {
+            assert_eq!(
+                bytes.len(),
+                arg.ty.size(),
+                "{}.{}",
+                described.name,
+                arg.name
+            );
+            assert_eq!(
+                &data[offset..offset + arg.ty.size()],
+                &bytes[..],
+                "{}.{}",
+                described.name,
+                arg.name
+            );
+            offset += arg.ty.size();
+        }
+        assert_eq!(offset, data.len(), "{}", described.name);
+        assert_eq!(StakingInstruction::unpack(&data), Ok(ix));
+    }
+}
+
+fn decimal_bytes(value: Decimal) -> Vec<u8> {
+    let mut bytes = vec![0; Decimal::LEN];
+    value.pack_into_slice(&mut bytes);
+    bytes
+}
+
+fn assert_fields(account: &IdlAccountType, data: &[u8], expected: &[(&str, Vec<u8>)]) {
+    let fields = account.field_offsets();
+    let (last, last_offset) = fields.last().unwrap();
+    assert_eq!(
+        last_offset + last.ty.size(),
+        account.size,
+        "{}",
+        account.name
+    );
+    assert_eq!(data.len(), account.size, "{}", account.name);
+
+    for (field, offset) in fields {
+        if let IdlType::Padding(_) = field.ty {
+            continue;
+        }
+        let bytes = &expected
+            .iter()
+            .find(|(name, _)| *name == field.name)
+            .unwrap_or_else(|| panic!("no expected value for {}", field.name))
+            .1;
+        assert_eq!(bytes.len(), field.ty.size(), "{}", field.name);
+        assert_eq!(
+            &data[offset..offset + field.ty.size()],
+            &bytes[..],
+            "{}",
+            field.name
+        );
+    }
+}
+
+#[test]
+fn staking_pool_layout_matches_pack() {
+    let pool = StakingPool {
+        version: 1,
+        owner_authority: key(),
+        admin_authority: key(),
+        reward_token_pool: key(),
+        last_update: 11,
+        end_time: 12,
+        earliest_reward_claim_time: 13,
+        duration: 14,
+        rate_per_slot: Decimal::from(15u64),
+        cumulative_rate: Decimal::from(16u64),
+        pool_size: 17,
+        bump_seed_staking_program: 18,
+        unallocated_reward_wads: Decimal::from(19u64),
+        start_time: 20,
+        deposit_before_start: true,
+        time_basis: TimeBasis::UnixTimestamp,
+        max_pool_size: 21,
+        max_deposit_per_account: 22,
+        allowlist_enabled: true,
+        gatekeeper: key(),
+        custodial: true,
+        early_claim_penalty_bps: 23,
+        early_claim_penalty_mode: EarlyClaimPenaltyMode::ReturnToPool,
+        vesting_cliff: 24,
+        vesting_duration: 25,
+        max_lock_duration: 26,
+        owed_reward_wads: Decimal::from(27u64),
+        owed_reward_tracked: true,
+        ..StakingPool::default()
+    };
+    let mut data = vec![0; StakingPool::LEN];
+    pool.pack_into_slice(&mut data);
+
+    assert_fields(
+        idl_account("StakingPool"),
+        &data,
+        &[
+            ("version", vec![1]),
+            ("owner_authority", pool.owner_authority.to_bytes().to_vec()),
+            ("admin_authority", pool.admin_authority.to_bytes().to_vec()),
+            (
+                "reward_token_pool",
+                pool.reward_token_pool.to_bytes().to_vec(),
+            ),
+            ("last_update", 11u64.to_le_bytes().to_vec()),
+            ("end_time", 12u64.to_le_bytes().to_vec()),
+            ("earliest_reward_claim_time", 13u64.to_le_bytes().to_vec()),
+            ("duration", 14u64.to_le_bytes().to_vec()),
+            ("rate_per_slot", decimal_bytes(pool.rate_per_slot)),
+            ("cumulative_rate", decimal_bytes(pool.cumulative_rate)),
+            ("pool_size", 17u64.to_le_bytes().to_vec()),
+            ("bump_seed_staking_program", vec![18]),
+            (
+                "unallocated_reward_wads",
+                decimal_bytes(pool.unallocated_reward_wads),
+            ),
+            ("start_time", 20u64.to_le_bytes().to_vec()),
+            ("deposit_before_start", vec![1]),
+            ("time_basis", vec![1]),
+            ("max_pool_size", 21u64.to_le_bytes().to_vec()),
+            ("max_deposit_per_account", 22u64.to_le_bytes().to_vec()),
+            ("allowlist_enabled", vec![1]),
+            ("gatekeeper", pool.gatekeeper.to_bytes().to_vec()),
+            ("custodial", vec![1]),
+            ("early_claim_penalty_bps", 23u16.to_le_bytes().to_vec()),
+            ("early_claim_penalty_mode", vec![1]),
+            ("vesting_cliff", 24u64.to_le_bytes().to_vec()),
+            ("vesting_duration", 25u64.to_le_bytes().to_vec()),
+            ("max_lock_duration", 26u64.to_le_bytes().to_vec()),
+            ("owed_reward_wads", decimal_bytes(pool.owed_reward_wads)),
+            ("owed_reward_tracked", vec![1]),
+        ],
+    );
+    assert_eq!(StakingPool::unpack_from_slice(&data).unwrap(), pool);
+}
+
+#[test]
+fn stake_account_layout_matches_pack() {
+    let stake_account = StakeAccount {
+        version: 1,
+        start_rate: Decimal::from(2u64),
+        owner: key(),
+        pool_pubkey: key(),
+        deposited_amount: 3,
+        unclaimed_reward_wads: Decimal::from(4u64),
+        lock_end: 5,
+        voted_until: 6,
+        ..StakeAccount::default()
+    };
+    let mut data = vec![0; StakeAccount::LEN];
+    stake_account.pack_into_slice(&mut data);
+
+    assert_fields(
+        idl_account("StakeAccount"),
+        &data,
+        &[
+            ("version", vec![1]),
+            ("start_rate", decimal_bytes(stake_account.start_rate)),
+            ("owner", stake_account.owner.to_bytes().to_vec()),
+            ("pool_pubkey", stake_account.pool_pubkey.to_bytes().to_vec()),
+            ("deposited_amount", 3u64.to_le_bytes().to_vec()),
+            (
+                "unclaimed_reward_wads",
+                decimal_bytes(stake_account.unclaimed_reward_wads),
+            ),
+            ("lock_end", 5u64.to_le_bytes().to_vec()),
+            ("voted_until", 6u64.to_le_bytes().to_vec()),
+        ],
+    );
+    assert_eq!(
+        StakeAccount::unpack_from_slice(&data).unwrap(),
+        stake_account
+    );
+}
+
+fn idl_reserved(name: &str) -> usize {
+    idl_account(name)
+        .fields
+        .iter()
+        .find_map(|field| match (field.name, &field.ty) {
+            ("reserved", IdlType::Padding(size)) => Some(*size),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("{} has no reserve", name))
+}
+
+#[test]
+fn reserves_match_packed_reserves() {
+    let pool = StakingPool::default();
+    assert_eq!(
+        pool.reserve_fields1.len()
+            + pool.reserve_fields2.len()
+            + pool.reserve_fields3.len()
+            + pool.reserve_fields4.len(),
+        idl_reserved("StakingPool")
+    );
+    let stake_account = StakeAccount::default();
+    assert_eq!(
+        stake_account.reserve_fields1.len()
+            + stake_account.reserve_fields2.len()
+            + stake_account.reserve_fields3.len()
+            + stake_account.reserve_fields4.len(),
+        idl_reserved("StakeAccount")
+    );
+    assert_eq!(
+        AllowlistEntry::default().reserve_fields.len(),
+        idl_reserved("AllowlistEntry")
+    );
+    assert_eq!(
+        VestingAccount::default().reserve_fields.len(),
+        idl_reserved("VestingAccount")
+    );
+}
+
+#[test]
+fn allowlist_entry_layout_matches_pack() {
+    let entry = AllowlistEntry {
+        version: 1,
+        staking_pool: key(),
+        owner: key(),
+        bump_seed: 2,
+        ..AllowlistEntry::default()
+    };
+    let mut data = vec![0; AllowlistEntry::LEN];
+    entry.pack_into_slice(&mut data);
+
+    assert_fields(
+        idl_account("AllowlistEntry"),
+        &data,
+        &[
+            ("version", vec![1]),
+            ("staking_pool", entry.staking_pool.to_bytes().to_vec()),
+            ("owner", entry.owner.to_bytes().to_vec()),
+            ("bump_seed", vec![2]),
+        ],
+    );
+    assert_eq!(AllowlistEntry::unpack_from_slice(&data).unwrap(), entry);
+}
+
+#[test]
+fn vesting_account_layout_matches_pack() {
+    let vesting_account = VestingAccount {
+        version: 1,
+        staking_pool: key(),
+        owner: key(),
+        bump_seed: 2,
+        vesting_start: 3,
+        total_amount: 4,
+        released_amount: 5,
+        unlocked_amount: 6,
+        cliff_start: 7,
+        ..VestingAccount::default()
+    };
+    let mut data = vec![0; VestingAccount::LEN];
+    vesting_account.pack_into_slice(&mut data);
+
+    assert_fields(
+        idl_account("VestingAccount"),
+        &data,
+        &[
+            ("version", vec![1]),
+            (
+                "staking_pool",
+                vesting_account.staking_pool.to_bytes().to_vec(),
+            ),
+            ("owner", vesting_account.owner.to_bytes().to_vec()),
+            ("bump_seed", vec![2]),
+            ("vesting_start", 3u64.to_le_bytes().to_vec()),
+            ("total_amount", 4u64.to_le_bytes().to_vec()),
+            ("released_amount", 5u64.to_le_bytes().to_vec()),
+            ("unlocked_amount", 6u64.to_le_bytes().to_vec()),
+            ("cliff_start", 7u64.to_le_bytes().to_vec()),
+        ],
+    );
+    assert_eq!(
+        VestingAccount::unpack_from_slice(&data).unwrap(),
+        vesting_account
+    );
+}
+
+#[test]
+fn reward_distributor_layout_matches_pack() {
+    let pool = DistributorPool {
+        staking_pool: key(),
+        weight: 9,
+        checkpoint: Decimal::from(10u64),
+        accrued_reward_wads: Decimal::from(11u64),
+    };
+    let distributor = RewardDistributor {
+        version: 1,
+        admin_authority: key(),
+        reward_token_vault: key(),
+        bump_seed: 2,
+        time_basis: TimeBasis::UnixTimestamp,
+        rate_per_slot: Decimal::from(3u64),
+        reward_per_weight: Decimal::from(4u64),
+        unallocated_reward_wads: Decimal::from(5u64),
+        last_update: 6,
+        end_time: 7,
+        total_weight: 8,
+        pools: vec![pool.clone()],
+    };
+    let mut data = vec![0; RewardDistributor::LEN];
+    distributor.pack_into_slice(&mut data);
+
+    let described = idl_account("RewardDistributor");
+    let pool_type = idl::TYPES
+        .iter()
+        .find(|ty| ty.name == "DistributorPool")
+        .unwrap();
+    let mut pools = [
+        pool.staking_pool.to_bytes().to_vec(),
+        9u64.to_le_bytes().to_vec(),
+        decimal_bytes(pool.checkpoint),
+        decimal_bytes(pool.accrued_reward_wads),
+    ]
+    .concat();
+    assert_eq!(pools.len(), pool_type.size);
+    pools.resize(pool_type.size * MAX_DISTRIBUTOR_POOLS, 0);
+
+    assert_fields(
+        described,
+        &data,
+        &[
+            ("version", vec![1]),
+            (
+                "admin_authority",
+                distributor.admin_authority.to_bytes().to_vec(),
+            ),
+            (
+                "reward_token_vault",
+                distributor.reward_token_vault.to_bytes().to_vec(),
+            ),
+            ("bump_seed", vec![2]),
+            ("time_basis", vec![1]),
+            ("rate_per_slot", decimal_bytes(distributor.rate_per_slot)),
+            (
+                "reward_per_weight",
+                decimal_bytes(distributor.reward_per_weight),
+            ),
+            (
+                "unallocated_reward_wads",
+                decimal_bytes(distributor.unallocated_reward_wads),
+            ),
+            ("last_update", 6u64.to_le_bytes().to_vec()),
+            ("end_time", 7u64.to_le_bytes().to_vec()),
+            ("total_weight", 8u64.to_le_bytes().to_vec()),
+            ("pools_len", vec![1]),
+            ("pools", pools),
+        ],
+    );
+    assert_eq!(
+        RewardDistributor::unpack_from_slice(&data).unwrap(),
+        distributor
+    );
+}
+
+#[test]
+fn gauge_layout_matches_pack() {
+    let gauge = Gauge {
+        version: 1,
+        admin_authority: key(),
+        governance_pool: key(),
+        reward_token_vault: key(),
+        bump_seed: 2,
+        time_basis: TimeBasis::UnixTimestamp,
+        epoch_duration: 3,
+        epoch: 4,
+        epoch_start: 5,
+        emission_per_epoch: 6,
+        pools: vec![GaugePool {
+            staking_pool: key(),
+            votes: 7,
+        }],
+    };
+    let mut data = vec![0; Gauge::LEN];
+    gauge.pack_into_slice(&mut data);
+
+    let mut pools = [
+        gauge.pools[0].staking_pool.to_bytes().to_vec(),
+        7u64.to_le_bytes().to_vec(),
+    ]
+    .concat();
+    pools.resize(pools.len() * MAX_GAUGE_POOLS, 0);
+
+    assert_fields(
+        idl_account("Gauge"),
+        &data,
+        &[
+            ("version", vec![1]),
+            ("admin_authority", gauge.admin_authority.to_bytes().to_vec()),
+            ("governance_pool", gauge.governance_pool.to_bytes().to_vec()),
+            (
+                "reward_token_vault",
+                gauge.reward_token_vault.to_bytes().to_vec(),
+            ),
+            ("bump_seed", vec![2]),
+            ("time_basis", vec![1]),
+            ("epoch_duration", 3u64.to_le_bytes().to_vec()),
+            ("epoch", 4u64.to_le_bytes().to_vec()),
+            ("epoch_start", 5u64.to_le_bytes().to_vec()),
+            ("emission_per_epoch", 6u64.to_le_bytes().to_vec()),
+            ("pools_len", vec![1]),
+            ("pools", pools),
+        ],
+    );
+    assert_eq!(Gauge::unpack_from_slice(&data).unwrap(), gauge);
+}
+
+#[test]
+fn gauge_vote_layout_matches_pack() {
+    let mut weights_bps = [0; MAX_GAUGE_POOLS];
+    weights_bps[1] = 2_500;
+    let vote = GaugeVote {
+        version: 1,
+        gauge: key(),
+        stake_account: key(),
+        bump_seed: 2,
+        committed_epoch: 3,
+        weights_bps,
+    };
+    let mut data = vec![0; GaugeVote::LEN];
+    vote.pack_into_slice(&mut data);
+
+    assert_fields(
+        idl_account("GaugeVote"),
+        &data,
+        &[
+            ("version", vec![1]),
+            ("gauge", vote.gauge.to_bytes().to_vec()),
+            ("stake_account", vote.stake_account.to_bytes().to_vec()),
+            ("bump_seed", vec![2]),
+            ("committed_epoch", 3u64.to_le_bytes().to_vec()),
+            (
+                "weights_bps",
+                weights_bps
+                    .iter()
+                    .flat_map(|weight| weight.to_le_bytes().to_vec())
+                    .collect(),
+            ),
+        ],
+    );
+    assert_eq!(GaugeVote::unpack_from_slice(&data).unwrap(), vote);
+}
+
+#[test]
+fn json_is_complete() {
+    let json: serde_json::Value = serde_json::from_str(&idl::to_json()).unwrap();
+    assert_eq!(
+        json["instructions"].as_array().unwrap().len(),
+        idl::INSTRUCTIONS.len()
+    );
+    assert_eq!(json["types"].as_array().unwrap().len(), idl::TYPES.len());
+    assert_eq!(
+        json["accounts"].as_array().unwrap().len(),
+        idl::ACCOUNTS.len()
+    );
+
+    let errors = json["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), idl::errors().len());
+    assert_eq!(errors[0]["name"], "InstructionUnpackError");
+    assert_eq!(
+        errors.last().unwrap()["code"].as_u64().unwrap() as usize,
+        errors.len() - 1
+    );
+}